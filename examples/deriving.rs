@@ -1,3 +1,5 @@
+#![allow(non_local_definitions)]
+
 extern crate serde;
 extern crate serde_json;
 extern crate serde_roundtrip;
@@ -19,7 +21,7 @@ fn main() {
 
     // Round-trip it via JSON.
     let json = serde_json::to_string(&msg).unwrap();
-    let round_tripped: Msg<String> = serde_json::from_str(&*json).unwrap();
+    let round_tripped: Msg<String> = serde_json::from_str(&json).unwrap();
 
     // This is the same as calling the `round_trip()` method.
     assert_eq!(round_tripped, msg.round_trip());
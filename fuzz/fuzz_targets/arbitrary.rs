@@ -0,0 +1,23 @@
+// Fuzz round trips of data built using arbitrary::Arbitrary, which reaches values,
+// such as maps with non-string keys, which the deserializers rarely produce.
+// Floats are only checked in bincode, since JSON serializes a NaN as null.
+
+#![no_main]
+
+#[macro_use] extern crate libfuzzer_sys;
+extern crate serde_roundtrip;
+
+use serde_roundtrip::conformance::RealFormat;
+use serde_roundtrip::fuzz::check_arbitrary;
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+type Source = (Vec<(u8, Option<String>)>, HashMap<u32, Vec<char>>, Option<Box<[i16]>>, (i128, bool));
+type Target = (VecDeque<(u8, Option<String>)>, BTreeMap<u32, Vec<char>>, Option<Vec<i16>>, (i128, bool));
+
+fuzz_target!(|bytes: &[u8]| {
+    check_arbitrary::<Source, Target>(bytes, RealFormat::ALL);
+    check_arbitrary::<Vec<(f32, f64)>, VecDeque<(f32, f64)>>(bytes, &[RealFormat::Bincode]);
+});
@@ -0,0 +1,26 @@
+// Fuzz the round trips of collections, including to other collections of the same kind.
+
+#![no_main]
+
+#[macro_use] extern crate libfuzzer_sys;
+extern crate serde_roundtrip;
+
+use serde_roundtrip::conformance::RealFormat;
+use serde_roundtrip::fuzz::check_bytes;
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::LinkedList;
+use std::collections::VecDeque;
+
+type Source = (Vec<u32>, VecDeque<String>, LinkedList<i8>, BTreeSet<u16>, HashMap<String, Vec<u8>>, [i64; 3], Result<u8, String>);
+type Target = (VecDeque<u32>, Vec<String>, Vec<i8>, HashSet<u16>, BTreeMap<String, Vec<u8>>, [i64; 3], Result<u8, String>);
+
+fuzz_target!(|bytes: &[u8]| {
+    for &format in RealFormat::ALL {
+        check_bytes::<Source, Source>(bytes, format);
+        check_bytes::<Source, Target>(bytes, format);
+    }
+});
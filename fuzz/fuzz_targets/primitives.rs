@@ -0,0 +1,22 @@
+// Fuzz the round trips of the primitive types, and of numbers to other numeric types.
+
+#![no_main]
+
+#[macro_use] extern crate libfuzzer_sys;
+extern crate serde_roundtrip;
+
+use serde_roundtrip::conformance::RealFormat;
+use serde_roundtrip::format::Json;
+use serde_roundtrip::fuzz::check_bytes;
+use serde_roundtrip::fuzz::check_bytes_in;
+
+type Source = ((bool, char, (), i8, i16, i32, i64, i128), (u8, u16, u32, u64, u128, f32, f64));
+
+fuzz_target!(|bytes: &[u8]| {
+    for &format in RealFormat::ALL {
+        check_bytes::<Source, Source>(bytes, format);
+    }
+    check_bytes_in::<(u8, i16, u32), (i64, i32, u64), _>(bytes, RealFormat::Json, Json);
+    check_bytes_in::<(i64, u64), (u8, i16), _>(bytes, RealFormat::Json, Json);
+    check_bytes_in::<(f32, u32), (f64, f64), _>(bytes, RealFormat::Json, Json);
+});
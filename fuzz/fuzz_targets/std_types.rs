@@ -0,0 +1,30 @@
+// Fuzz the round trips of the types in std, such as times, addresses, wrappers and ranges.
+
+#![no_main]
+
+#[macro_use] extern crate libfuzzer_sys;
+extern crate serde_roundtrip;
+
+use serde_roundtrip::conformance::RealFormat;
+use serde_roundtrip::fuzz::check_bytes;
+
+use std::cmp::Reverse;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::num::NonZeroU32;
+use std::num::Wrapping;
+use std::ops::Bound;
+use std::ops::Range;
+use std::ops::RangeInclusive;
+use std::time::Duration;
+use std::time::SystemTime;
+
+type Source = ((Duration, SystemTime, IpAddr, SocketAddr), (NonZeroU32, Wrapping<u8>, Reverse<i16>, Range<u32>, RangeInclusive<u8>, Bound<i32>));
+type Target = ((Duration, SystemTime, IpAddr, SocketAddr), (u32, Wrapping<u8>, Reverse<i16>, Range<u32>, RangeInclusive<u8>, Bound<i32>));
+
+fuzz_target!(|bytes: &[u8]| {
+    for &format in RealFormat::ALL {
+        check_bytes::<Source, Source>(bytes, format);
+        check_bytes::<Source, Target>(bytes, format);
+    }
+});
@@ -0,0 +1,31 @@
+// Fuzz the round trips of strings, byte strings and the smart pointers which hold them.
+
+#![no_main]
+
+#[macro_use] extern crate libfuzzer_sys;
+extern crate serde_bytes;
+extern crate serde_roundtrip;
+
+use serde_bytes::ByteBuf;
+use serde_roundtrip::conformance::RealFormat;
+use serde_roundtrip::format::Bincode;
+use serde_roundtrip::format::Json;
+use serde_roundtrip::fuzz::check_bytes;
+use serde_roundtrip::fuzz::check_bytes_in;
+
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+
+type Source = (String, Box<str>, Option<Rc<str>>, Arc<String>, ByteBuf, Cow<'static, str>);
+type Target = (PathBuf, String, Option<String>, Box<str>, ByteBuf, Rc<str>);
+
+fuzz_target!(|bytes: &[u8]| {
+    for &format in RealFormat::ALL {
+        check_bytes::<Source, Source>(bytes, format);
+        check_bytes::<Source, Target>(bytes, format);
+    }
+    check_bytes_in::<(char, PathBuf, ByteBuf), (String, String, Vec<u8>), _>(bytes, RealFormat::Json, Json);
+    check_bytes_in::<ByteBuf, Vec<u8>, _>(bytes, RealFormat::Bincode, Bincode);
+});
@@ -11,6 +11,7 @@ use syn::AngleBracketedParameterData;
 use syn::Generics;
 use syn::Ident;
 use syn::Lifetime;
+use syn::LifetimeDef;
 use syn::Path;
 use syn::PathParameters;
 use syn::PathSegment;
@@ -21,7 +22,7 @@ use syn::TyParam;
 use syn::TyParamBound;
 use syn::WhereClause;
 
-#[proc_macro_derive(RoundTrip)]
+#[proc_macro_derive(RoundTrip, attributes(roundtrip))]
 pub fn round_trip(input: TokenStream) -> TokenStream {
     let s = input.to_string();
     let ast = syn::parse_macro_input(&s).unwrap();
@@ -74,6 +75,127 @@ impl<'a> Renaming<'a> {
     }
 }
 
+// Collect the indices of the type parameters which occur in a type.
+
+struct TyParamCollector<'a> {
+    original: &'a Generics,
+    indices: Vec<usize>,
+}
+
+impl<'a> Folder for TyParamCollector<'a> {
+    fn fold_path(&mut self, path: Path) -> Path {
+        if let Some((segment, rest)) = path.segments.split_first() {
+            if rest.is_empty() && segment.parameters.is_empty() {
+                let index = self.original.ty_params.iter().position(|original| original.ident == segment.ident);
+                self.indices.extend(index);
+            }
+        }
+        noop_fold_path(self, path)
+    }
+}
+
+// Collect the lifetimes which occur in a type.
+
+struct LifetimeCollector {
+    lifetimes: Vec<Lifetime>,
+}
+
+impl Folder for LifetimeCollector {
+    fn fold_lifetime(&mut self, lifetime: Lifetime) -> Lifetime {
+        if !self.lifetimes.contains(&lifetime) {
+            self.lifetimes.push(lifetime.clone());
+        }
+        lifetime
+    }
+}
+
+// Replace the lifetimes in a path by 'static, so that it can be used in a constant.
+
+struct StaticLifetimes;
+
+impl Folder for StaticLifetimes {
+    fn fold_lifetime(&mut self, _: Lifetime) -> Lifetime {
+        Lifetime::new("'static")
+    }
+}
+
+// The fields of a struct, or of all the variants of an enum
+
+fn all_fields(ast: &syn::MacroInput) -> Vec<&syn::Field> {
+    match ast.body {
+        syn::Body::Struct(ref data) => data.fields().iter().collect(),
+        syn::Body::Enum(ref variants) => variants.iter().flat_map(|variant| variant.data.fields()).collect(),
+    }
+}
+
+// As in serde, the type parameters of fields which are initialized by Default::default()
+// when deserializing are bounded by Default.
+
+fn default_ty_params(ast: &syn::MacroInput) -> Vec<usize> {
+    let mut collector = TyParamCollector { original: &ast.generics, indices: vec![] };
+    for field in all_fields(ast) {
+        if let DefaultValue::Default = FieldAttrs::new(field).default {
+            collector.fold_ty(field.ty.clone());
+        }
+    }
+    collector.indices
+}
+
+// As in serde, the lifetimes which are borrowed from the deserializer are those of fields
+// of type &str or &[u8], or an option of one, unless the field is skipped when deserializing,
+// and those given by #[serde(borrow)] or #[serde(borrow = "'a + 'b")].
+
+fn borrowed_lifetimes(ast: &syn::MacroInput) -> Vec<usize> {
+    let mut collector = LifetimeCollector { lifetimes: vec![] };
+    for field in all_fields(ast) {
+        let attrs = FieldAttrs::new(field);
+        if attrs.skip_deserializing {
+            continue;
+        }
+        match attrs.borrow {
+            Borrow::Implicit if !is_implicitly_borrowed(&field.ty) => (),
+            Borrow::Implicit | Borrow::All => { collector.fold_ty(field.ty.clone()); },
+            Borrow::Lifetimes(lifetimes) => collector.lifetimes.extend(lifetimes),
+        }
+    }
+    ast.generics.lifetimes.iter()
+        .enumerate()
+        .filter(|&(_, lifetime_def)| collector.lifetimes.contains(&lifetime_def.lifetime))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+fn is_implicitly_borrowed(ty: &Ty) -> bool {
+    match *ty {
+        Ty::Path(None, ref path) => match path.segments.last() {
+            Some(&PathSegment { ref ident, parameters: PathParameters::AngleBracketed(ref data) }) if ident == "Option" && data.types.len() == 1 => {
+                is_implicitly_borrowed_reference(&data.types[0])
+            },
+            _ => false,
+        },
+        _ => is_implicitly_borrowed_reference(ty),
+    }
+}
+
+fn is_implicitly_borrowed_reference(ty: &Ty) -> bool {
+    match *ty {
+        Ty::Rptr(_, ref referent) => match (&referent.mutability, &referent.ty) {
+            (syn::Mutability::Immutable, Ty::Slice(element)) => is_primitive(element, "u8"),
+            (syn::Mutability::Immutable, referent) => is_primitive(referent, "str"),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn is_primitive(ty: &Ty, name: &str) -> bool {
+    match *ty {
+        Ty::Path(None, ref path) => !path.global && path.segments.len() == 1
+            && path.segments[0].ident == name && path.segments[0].parameters.is_empty(),
+        _ => false,
+    }
+}
+
 // Convert an ident with its generic parameters to a path
 
 fn generic_path(ident: &Ident, generics: &Generics) -> Path {
@@ -94,7 +216,24 @@ fn generic_path(ident: &Ident, generics: &Generics) -> Path {
     }
 }
 
-// A type bound
+// A type bound, and a where clause predicate
+
+fn where_predicate(tokens: quote::Tokens) -> syn::WherePredicate {
+    let text = quote! { where #tokens };
+    let where_clause = syn::parse_where_clause(text.as_str()).expect("Unexpected parse error");
+    where_clause.predicates.into_iter().next().expect("Unexpected empty where clause")
+}
+
+// Data which is consumed by into_round_trip does not outlive its own lifetimes, so they must
+// outlive the lifetimes which the target borrows, for example 'a0: 'b0 as well as 'de: 'b0.
+fn source_outlives_borrowed(generics: &mut Generics, source: &Generics, de_lifetime: &LifetimeDef) {
+    for lifetime_def in &source.lifetimes {
+        let lifetime = &lifetime_def.lifetime;
+        for borrowed in &de_lifetime.bounds {
+            generics.where_clause.predicates.push(where_predicate(quote! { #lifetime: #borrowed }));
+        }
+    }
+}
 
 fn ty_param_bound(text: &str) -> TyParamBound {
     TyParamBound::Trait(
@@ -106,123 +245,1305 @@ fn ty_param_bound(text: &str) -> TyParamBound {
     )
 }
 
-// Derive a RoundTrip implementation
-
-fn impl_round_trip(ast: &syn::MacroInput) -> quote::Tokens {
-    let name = &ast.ident;
-
-    // If the original is Foo<'l, X, Y>, the target type is Foo<'b0, T0, T1>.
-    let mut target_renaming = Renaming { original: &ast.generics, lifetime_prefix: "'b", ty_param_prefix: "T" };
-    let mut target_generics = target_renaming.fold_generics(ast.generics.clone());
-    for ty_param in target_generics.ty_params.iter_mut() {
-        ty_param.bounds.push(ty_param_bound("::serde::Deserialize"));
-    }
-    let target_where_clause = target_generics.where_clause.clone();
-    let target_path = generic_path(&ast.ident, &target_generics);
-
-    // The target type parameter is T: SameDeserialization<SameAs=Foo<'b0, T0, T1>>.
-    let target_ty_param_bound = quote! { ::serde_roundtrip::SameDeserialization<SameAs=#target_path> };
-    let target_ty_param = TyParam {
-        attrs: vec![],
-        ident: Ident::from("T"),
-        bounds: vec![ty_param_bound(target_ty_param_bound.as_str())],
-        default: None,
-    };
+// Bound each source type parameter by a round trip trait, for example S0: RoundTrip<'de, T0>,
+// or S0: RoundTripIn<'de, F, T0> if the trait is parameterized by a format.
 
-    // If the original is Foo<'l, X, Y>, the source type is Foo<'a0, S0, S1>.
-    let mut source_renaming = Renaming { original: &ast.generics, lifetime_prefix: "'a", ty_param_prefix: "S" };
-    let mut source_generics = source_renaming.fold_generics(ast.generics.clone());
-    for (ty_param, target_ty_param) in source_generics.ty_params.iter_mut().zip(target_generics.ty_params.iter()) {
+fn bounded_source_generics(source_generics: &Generics, target_generics: &Generics, trait_name: &str, format: Option<&Ident>) -> Generics {
+    let mut result = source_generics.clone();
+    for (ty_param, target_ty_param) in result.ty_params.iter_mut().zip(target_generics.ty_params.iter()) {
+        let trait_ident = Ident::from(trait_name);
         let target_ty_param_ident = &target_ty_param.ident;
-        let text = quote! { ::serde_roundtrip::RoundTrip<#target_ty_param_ident> };
+        let text = match format {
+            Some(format) => quote! { ::serde_roundtrip::#trait_ident<'de, #format, #target_ty_param_ident> },
+            None => quote! { ::serde_roundtrip::#trait_ident<'de, #target_ty_param_ident> },
+        };
         ty_param.bounds.push(ty_param_bound(text.as_str()));
     }
-    let source_path = generic_path(&ast.ident, &source_generics);
+    result
+}
 
-    // The whole thing is parameterized by 'a0, 'b0, S0, S1, T0, T1, T.
-    let all_generics = Generics {
-        lifetimes: source_generics.lifetimes.iter().cloned()
+// The generics of an implementation are 'de, 'a0, 'b0, S0, S1, T0, T1, T.
+
+fn impl_generics(de_lifetime: &LifetimeDef, source_generics: &Generics, target_generics: &Generics, target_ty_param: &TyParam) -> Generics {
+    Generics {
+        lifetimes: ::std::iter::once(de_lifetime.clone())
+            .chain(source_generics.lifetimes.iter().cloned())
             .chain(target_generics.lifetimes.iter().cloned())
             .collect::<Vec<_>>(),
         ty_params: source_generics.ty_params.iter().cloned()
             .chain(target_generics.ty_params.iter().cloned())
-            .chain(::std::iter::once(target_ty_param))
+            .chain(::std::iter::once(target_ty_param.clone()))
             .collect::<Vec<_>>(),
         where_clause: WhereClause {
             predicates: source_generics.where_clause.predicates.iter().cloned()
                 .chain(target_generics.where_clause.predicates.iter().cloned())
                 .collect::<Vec<_>>(),
         },
-    };
-    let all_where_clause = all_generics.where_clause.clone();
+    }
+}
+
+// The default value of a field which is missing from the serialized data,
+// as given by #[serde(default)] or #[serde(default = "path")]
+
+enum DefaultValue {
+    None,
+    Default,
+    Path(Path),
+}
+
+impl DefaultValue {
+    fn value(&self) -> Option<quote::Tokens> {
+        match *self {
+            DefaultValue::None => None,
+            DefaultValue::Default => Some(quote! { ::serde_roundtrip::export::Default::default() }),
+            DefaultValue::Path(ref path) => Some(quote! { #path() }),
+        }
+    }
+}
+
+// The items of the #[serde(...)] or #[roundtrip(...)] attributes
+
+fn attr_items<'a>(attrs: &'a [syn::Attribute], attr_name: &str) -> Vec<&'a syn::MetaItem> {
+    attrs.iter()
+        .filter_map(|attr| match attr.value {
+            syn::MetaItem::List(ref name, ref items) if name == attr_name => Some(items),
+            _ => None,
+        })
+        .flat_map(|items| items.iter())
+        .filter_map(|item| match *item {
+            syn::NestedMetaItem::MetaItem(ref item) => Some(item),
+            syn::NestedMetaItem::Literal(_) => None,
+        })
+        .collect()
+}
+
+fn serde_items(attrs: &[syn::Attribute]) -> Vec<&syn::MetaItem> {
+    attr_items(attrs, "serde")
+}
+
+fn roundtrip_items(attrs: &[syn::Attribute]) -> Vec<&syn::MetaItem> {
+    attr_items(attrs, "roundtrip")
+}
+
+fn lit_string(name: &Ident, lit: &syn::Lit) -> String {
+    match *lit {
+        syn::Lit::Str(ref value, _) => value.clone(),
+        _ => panic!("Expected a string for attribute {}", name),
+    }
+}
+
+fn lit_path(name: &Ident, lit: &syn::Lit) -> Path {
+    syn::parse_path(&lit_string(name, lit))
+        .unwrap_or_else(|err| panic!("Expected a path for attribute {}: {}", name, err))
+}
+
+// The representation of an enum, as given by #[serde(tag = "...")], #[serde(content = "...")]
+// or #[serde(untagged)]
+
+enum EnumTag {
+    External,
+    Internal,
+    Adjacent,
+    Untagged,
+}
+
+// The serde and roundtrip attributes of a struct or enum which affect round tripping
+
+struct ContainerAttrs {
+    default: DefaultValue,
+    // The binding of __default, which missing fields are taken from if the container has a default
+    let_default: quote::Tokens,
+    tag: EnumTag,
+    via_serde: bool,
+    // The target type given by #[roundtrip(target = "...")], if it differs from the source type
+    target: Option<Path>,
+    // Whether a tuple struct round trips to and from a tuple, given by #[roundtrip(tuple)]
+    tuple: bool,
+    // The fields of the target which the source does not have, given by #[roundtrip(missing = "...")]
+    missing: Vec<Ident>,
+    // Whether a field is an option in the target, which is only sound in self-describing formats
+    some: bool,
+    // Whether a field is skipped or renamed on only one side, or has skip_serializing_if, so
+    // whether it is in the serialized data is only known in self-describing formats
+    skips: bool,
+    // Whether the type only round trips in self-describing formats, for example because a field
+    // is an internally tagged enum, given by #[roundtrip(self_describing)]
+    self_describing: bool,
+    // The previous version of the type, which round trips to it, given by #[roundtrip(from = "...")]
+    from: Option<Path>,
+    // Whether the type has #[serde(deny_unknown_fields)]
+    deny_unknown_fields: bool,
+    // Whether other versions of the struct round trip to or from it, given by #[roundtrip(versioned)],
+    // so it needs the hidden functions which they use
+    versioned: bool,
+}
+
+impl ContainerAttrs {
+    fn new(ast: &syn::MacroInput, target_path: &Path) -> ContainerAttrs {
+        let mut default = DefaultValue::None;
+        let mut tag = false;
+        let mut content = false;
+        let mut untagged = false;
+        let mut deny_unknown_fields = false;
+        for item in serde_items(&ast.attrs) {
+            match *item {
+                syn::MetaItem::Word(ref name) if name == "default" => default = DefaultValue::Default,
+                syn::MetaItem::NameValue(ref name, ref lit) if name == "default" => default = DefaultValue::Path(lit_path(name, lit)),
+                syn::MetaItem::NameValue(ref name, _) if name == "tag" => tag = true,
+                syn::MetaItem::NameValue(ref name, _) if name == "content" => content = true,
+                syn::MetaItem::Word(ref name) if name == "untagged" => untagged = true,
+                syn::MetaItem::Word(ref name) if name == "deny_unknown_fields" => deny_unknown_fields = true,
+                _ => (),
+            }
+        }
+        let mut via_serde = false;
+        let mut target = None;
+        let mut tuple = false;
+        let mut missing = vec![];
+        let mut from = None;
+        let mut versioned = false;
+        let mut self_describing = false;
+        for item in roundtrip_items(&ast.attrs) {
+            match *item {
+                syn::MetaItem::Word(ref name) if name == "self_describing" => self_describing = true,
+                syn::MetaItem::Word(ref name) if name == "via_serde" => via_serde = true,
+                syn::MetaItem::Word(ref name) if name == "versioned" => versioned = true,
+                syn::MetaItem::Word(ref name) if name == "tuple" => tuple = true,
+                syn::MetaItem::NameValue(ref name, ref lit) if name == "missing" => {
+                    missing = lit_string(name, lit).split(',')
+                        .map(|field| Ident::from(field.trim()))
+                        .collect();
+                },
+                syn::MetaItem::NameValue(ref name, ref lit) if name == "target" => target = Some(lit_path(name, lit)),
+                syn::MetaItem::NameValue(ref name, ref lit) if name == "from" => from = Some(lit_path(name, lit)),
+                _ => panic!("Unsupported roundtrip attribute {}", quote! { #item }),
+            }
+        }
+        // The default of the source is only used when deserializing the source, not the target.
+        if target.is_some() {
+            default = DefaultValue::None;
+        }
+        let let_default = match default.value() {
+            Some(value) => quote! { let __default: #target_path = #value; },
+            None => quote! {},
+        };
+        let tag = match (tag, content, untagged) {
+            (false, false, false) => EnumTag::External,
+            (true, false, false) => EnumTag::Internal,
+            (true, true, false) => EnumTag::Adjacent,
+            (false, false, true) => EnumTag::Untagged,
+            _ => panic!("Unsupported combination of serde enum attributes tag, content and untagged"),
+        };
+        if let EnumTag::Untagged = tag {
+            if !via_serde {
+                panic!("derive(RoundTrip) does not support untagged enums, since a variant may deserialize \
+                        as an earlier variant. Use #[roundtrip(via_serde)] to round trip them via serde.");
+            }
+        }
+        if tuple && (via_serde || target.is_some()) {
+            panic!("#[roundtrip(tuple)] cannot be combined with via_serde or target");
+        }
+        let some = all_fields(ast).into_iter().any(|field| FieldAttrs::new(field).some);
+        let skips = all_fields(ast).into_iter().any(|field| {
+            let attrs = FieldAttrs::new(field);
+            attrs.skip_serializing != attrs.skip_deserializing || attrs.skip_serializing_if.is_some() || attrs.ser_name != attrs.de_name
+        });
+        if (some || !missing.is_empty()) && (via_serde || target.is_none()) {
+            panic!("#[roundtrip(some)] and #[roundtrip(missing = \"...\")] need a #[roundtrip(target = \"...\")] with different fields");
+        }
+        if !missing.is_empty() {
+            if let syn::Body::Struct(syn::VariantData::Struct(_)) = ast.body {} else {
+                panic!("#[roundtrip(missing = \"...\")] is only supported for structs with named fields");
+            }
+        }
+        let migrates = all_fields(ast).into_iter().any(|field| {
+            let attrs = FieldAttrs::new(field);
+            attrs.added || attrs.from.is_some()
+        });
+        if migrates && from.is_none() {
+            panic!("#[roundtrip(added)] and #[roundtrip(from = \"...\")] on a field need a #[roundtrip(from = \"...\")] on the struct");
+        }
+        if from.is_some() {
+            if via_serde || tuple || target.is_some() {
+                panic!("#[roundtrip(from = \"...\")] cannot be combined with via_serde, tuple or target");
+            }
+            if let syn::Body::Struct(syn::VariantData::Struct(_)) = ast.body {} else {
+                panic!("#[roundtrip(from = \"...\")] is only supported for structs with named fields");
+            }
+            if !ast.generics.lifetimes.is_empty() || !ast.generics.ty_params.is_empty() {
+                panic!("#[roundtrip(from = \"...\")] is only supported for structs without generic parameters");
+            }
+        }
+        if versioned {
+            if target.is_some() {
+                panic!("#[roundtrip(versioned)] cannot be combined with target");
+            }
+            if let syn::Body::Struct(syn::VariantData::Struct(_)) = ast.body {} else {
+                panic!("#[roundtrip(versioned)] is only supported for structs with named fields");
+            }
+        }
+        ContainerAttrs { default, let_default, tag, via_serde, target, tuple, missing, some, skips, self_describing, from, deny_unknown_fields, versioned }
+    }
+
+    // The bound needed by __default, for example Foo<'b0, T0, T1>: Default
+    fn bound(&self, target_path: &Path) -> Option<syn::WherePredicate> {
+        match self.default {
+            DefaultValue::Default => Some(where_predicate(quote! { #target_path: ::serde_roundtrip::export::Default })),
+            _ => None,
+        }
+    }
+
+    // Whether the round trip is only sound in self-describing formats, which is the case
+    // if a field is an option in the target or is skipped, or for an enum which is not externally tagged,
+    // since serde deserializes its tag or content with deserialize_any or deserialize_identifier.
+    fn only_self_describing(&self) -> bool {
+        match self.tag {
+            EnumTag::External => self.self_describing || self.some || self.skips,
+            EnumTag::Internal | EnumTag::Adjacent | EnumTag::Untagged => true,
+        }
+    }
+
+    // The path used to construct the target, for example Foo, or Msg if the target is Msg<'b>.
+    fn constructor(&self, name: &Ident) -> quote::Tokens {
+        match self.target {
+            Some(ref target) => {
+                let mut target = target.clone();
+                if let Some(segment) = target.segments.last_mut() {
+                    segment.parameters = PathParameters::none();
+                }
+                quote! { #target }
+            },
+            None => quote! { #name },
+        }
+    }
+
+    // The fields of the target which the source does not have, which are initialized as the
+    // target would initialize them if they were missing, for example
+    // retries: Msg::__roundtrip_default_retries()?
+    fn missing_fields(&self, constructor: &quote::Tokens, method: &Method) -> Vec<quote::Tokens> {
+        self.missing.iter()
+            .map(|ident| {
+                let default_fn = default_fn(ident);
+                let value = method.unwrap(&format!("field {}", ident), quote! { #constructor::#default_fn() });
+                quote! { #ident: #value }
+            })
+            .collect()
+    }
+
+    // Whether a field has no counterpart in the target, which is the case for
+    // fields which are skipped when serializing to a different target type
+    fn omits(&self, field: &syn::Field) -> bool {
+        self.target.is_some() && FieldAttrs::new(field).skip_serializing
+    }
+
+    // The name of a field in the target, which is its serialized name if the target type is different
+    fn target_field(&self, field: &syn::Field) -> Option<Ident> {
+        match (&self.target, FieldAttrs::new(field).ser_name) {
+            (&Some(_), Some(name)) => {
+                let valid = name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+                    && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+                if !valid {
+                    panic!("derive(RoundTrip) cannot match field renamed to {:?} with a field of the target type", name);
+                }
+                Some(Ident::from(name))
+            },
+            _ => field.ident.clone(),
+        }
+    }
+}
+
+// The lifetimes which a field borrows from the deserializer, which are given by #[serde(borrow)]
+// or #[serde(borrow = "'a + 'b")], or are implicit for fields of type &str or &[u8]
+
+enum Borrow {
+    Implicit,
+    All,
+    Lifetimes(Vec<Lifetime>),
+}
+
+// The serde and roundtrip attributes of a field which affect round tripping
+
+struct FieldAttrs {
+    ser_name: Option<String>,
+    de_name: Option<String>,
+    skip_serializing: bool,
+    skip_serializing_if: Option<Path>,
+    skip_deserializing: bool,
+    default: DefaultValue,
+    serialize_with: Option<Path>,
+    deserialize_with: Option<Path>,
+    borrow: Borrow,
+    via_serde: bool,
+    // Whether the field is an option in the target, given by #[roundtrip(some)]
+    some: bool,
+    // The other names which the field is deserialized from, given by #[serde(alias = "...")]
+    aliases: Vec<String>,
+    // Whether the field is not in the previous version, given by #[roundtrip(added)]
+    added: bool,
+    // The field of the previous version it comes from, given by #[roundtrip(from = "...")]
+    from: Option<Ident>,
+}
+
+impl FieldAttrs {
+    fn new(field: &syn::Field) -> FieldAttrs {
+        let mut result = FieldAttrs {
+            ser_name: None,
+            de_name: None,
+            skip_serializing: false,
+            skip_serializing_if: None,
+            skip_deserializing: false,
+            default: DefaultValue::None,
+            serialize_with: None,
+            deserialize_with: None,
+            borrow: Borrow::Implicit,
+            via_serde: false,
+            some: false,
+            aliases: vec![],
+            added: false,
+            from: None,
+        };
+        for item in serde_items(&field.attrs) {
+            match *item {
+                syn::MetaItem::NameValue(ref name, ref lit) if name == "rename" => {
+                    result.ser_name = Some(lit_string(name, lit));
+                    result.de_name = Some(lit_string(name, lit));
+                },
+                syn::MetaItem::List(ref name, ref items) if name == "rename" => {
+                    for item in items {
+                        match *item {
+                            syn::NestedMetaItem::MetaItem(syn::MetaItem::NameValue(ref name, ref lit)) if name == "serialize" => result.ser_name = Some(lit_string(name, lit)),
+                            syn::NestedMetaItem::MetaItem(syn::MetaItem::NameValue(ref name, ref lit)) if name == "deserialize" => result.de_name = Some(lit_string(name, lit)),
+                            _ => (),
+                        }
+                    }
+                },
+                syn::MetaItem::Word(ref name) if name == "skip" => {
+                    result.skip_serializing = true;
+                    result.skip_deserializing = true;
+                },
+                syn::MetaItem::NameValue(ref name, ref lit) if name == "alias" => result.aliases.push(lit_string(name, lit)),
+                syn::MetaItem::Word(ref name) if name == "skip_serializing" => result.skip_serializing = true,
+                syn::MetaItem::Word(ref name) if name == "skip_deserializing" => result.skip_deserializing = true,
+                syn::MetaItem::NameValue(ref name, ref lit) if name == "skip_serializing_if" => result.skip_serializing_if = Some(lit_path(name, lit)),
+                syn::MetaItem::Word(ref name) if name == "default" => result.default = DefaultValue::Default,
+                syn::MetaItem::NameValue(ref name, ref lit) if name == "default" => result.default = DefaultValue::Path(lit_path(name, lit)),
+                syn::MetaItem::NameValue(ref name, ref lit) if name == "serialize_with" => result.serialize_with = Some(lit_path(name, lit)),
+                syn::MetaItem::NameValue(ref name, ref lit) if name == "deserialize_with" => result.deserialize_with = Some(lit_path(name, lit)),
+                syn::MetaItem::NameValue(ref name, ref lit) if name == "with" => {
+                    let mut ser_path = lit_path(name, lit);
+                    ser_path.segments.push(PathSegment::from("serialize"));
+                    result.serialize_with = Some(ser_path);
+                    let mut de_path = lit_path(name, lit);
+                    de_path.segments.push(PathSegment::from("deserialize"));
+                    result.deserialize_with = Some(de_path);
+                },
+                syn::MetaItem::Word(ref name) if name == "borrow" => result.borrow = Borrow::All,
+                syn::MetaItem::NameValue(ref name, ref lit) if name == "borrow" => {
+                    let lifetimes = lit_string(name, lit).split('+')
+                        .map(|lifetime| Lifetime::new(lifetime.trim()))
+                        .collect();
+                    result.borrow = Borrow::Lifetimes(lifetimes);
+                },
+                _ => (),
+            }
+        }
+        for item in roundtrip_items(&field.attrs) {
+            match *item {
+                syn::MetaItem::Word(ref name) if name == "via_serde" => result.via_serde = true,
+                syn::MetaItem::Word(ref name) if name == "some" => result.some = true,
+                syn::MetaItem::Word(ref name) if name == "added" => result.added = true,
+                syn::MetaItem::NameValue(ref name, ref lit) if name == "from" => result.from = Some(Ident::from(lit_string(name, lit))),
+                _ => panic!("Unsupported roundtrip attribute {}", quote! { #item }),
+            }
+        }
+        // As in serde, a field which is skipped when deserializing is initialized by default.
+        if result.skip_deserializing {
+            if let DefaultValue::None = result.default {
+                result.default = DefaultValue::Default;
+            }
+        }
+        // Tuple fields are positional, so skipping a field on only one side shifts the others.
+        if field.ident.is_none() && (result.skip_serializing || result.skip_serializing_if.is_some()) != result.skip_deserializing {
+            panic!("derive(RoundTrip) does not support tuple fields which are skipped when only serializing or only deserializing");
+        }
+        result
+    }
+
+    // Whether the field is round tripped by its own round trip method
+    fn is_plain(&self) -> bool {
+        !self.skip_serializing && self.skip_serializing_if.is_none() && !self.skip_deserializing
+            && self.ser_name == self.de_name && self.serialize_with.is_none() && self.deserialize_with.is_none()
+    }
+}
+
+// The round trip of a field, wrapped in ViaSerde if it has a #[roundtrip(via_serde)] attribute
+
+fn field_source(attrs: &FieldAttrs, tokens: quote::Tokens, by_value: bool) -> quote::Tokens {
+    if !attrs.via_serde {
+        tokens
+    } else if by_value {
+        quote! { ::serde_roundtrip::via_serde::ViaSerde(#tokens) }
+    } else {
+        quote! { ::serde_roundtrip::via_serde::ViaSerde(&#tokens) }
+    }
+}
+
+// A round trip method, which is one of round_trip, try_round_trip, round_trip_in or into_round_trip.
+
+struct Method {
+    ident: Ident,
+    args: quote::Tokens,
+    fallible: bool,
+    by_value: bool,
+}
+
+impl Method {
+    fn new(name: &str, args: quote::Tokens, fallible: bool, by_value: bool) -> Method {
+        Method { ident: Ident::from(name), args, fallible, by_value }
+    }
+
+    // The round trip of a field, for example x.round_trip() or x.round_trip_in(format)?,
+    // where tokens is the field and reference is a reference to it
+    fn field(&self, container: &ContainerAttrs, field: &syn::Field, tokens: quote::Tokens, reference: quote::Tokens) -> quote::Tokens {
+        let attrs = FieldAttrs::new(field);
+        // If the target type is different, the attributes which affect deserialization are the target's, not ours.
+        let same_target = container.target.is_none();
+        if same_target && attrs.skip_deserializing {
+            let value = attrs.default.value();
+            return quote! { { let _ = #tokens; #value } };
+        }
+        let missing = self.missing(container, field, &attrs);
+        if same_target && (attrs.skip_serializing || attrs.ser_name != attrs.de_name) {
+            return quote! { { let _ = #tokens; #missing } };
+        }
+        let deserialize_with = if same_target { attrs.deserialize_with.as_ref() } else { None };
+        let present = if attrs.serialize_with.is_some() || deserialize_with.is_some() {
+            let serialize = match attrs.serialize_with {
+                Some(ref path) => quote! { #path },
+                None => quote! { ::serde::Serialize::serialize },
+            };
+            let deserialize = match deserialize_with {
+                Some(path) => quote! { #path },
+                None => quote! { ::serde::Deserialize::deserialize },
+            };
+            self.unwrap(&field_description(field), quote! { ::serde_roundtrip::via_serde::round_trip_with(#reference, #serialize, #deserialize) })
+        } else {
+            let ident = &self.ident;
+            let args = &self.args;
+            let source = field_source(&attrs, tokens, self.by_value);
+            if self.fallible {
+                quote! { #source.#ident(#args)? }
+            } else {
+                quote! { #source.#ident(#args) }
+            }
+        };
+        // A field which the format serializes as null, such as (), is deserialized as None.
+        let present = if attrs.some {
+            quote! {
+                if ::serde_roundtrip::export::serializes_as_null(#reference, format) {
+                    ::serde_roundtrip::export::Option::None
+                } else {
+                    ::serde_roundtrip::export::Option::Some(#present)
+                }
+            }
+        } else {
+            present
+        };
+        match attrs.skip_serializing_if {
+            Some(ref path) => quote! { if #path(#reference) { #missing } else { #present } },
+            None => present,
+        }
+    }
+
+    // The value of a field which is missing from the serialized data, which is its default
+    // if it has one, or the result of deserializing a missing field.
+    // The defaults of a different target type are not known, so are not used.
+    fn missing(&self, container: &ContainerAttrs, field: &syn::Field, attrs: &FieldAttrs) -> quote::Tokens {
+        let (default, de_name) = match container.target {
+            None => (attrs.default.value(), &attrs.de_name),
+            Some(_) => (None, &attrs.ser_name),
+        };
+        if let Some(value) = default {
+            value
+        } else if let (&DefaultValue::None, _) | (_, &None) = (&container.default, &field.ident) {
+            let name = de_name.clone()
+                .or_else(|| field.ident.as_ref().map(Ident::to_string))
+                .unwrap_or_default();
+            self.unwrap(&field_description(field), quote! { ::serde_roundtrip::via_serde::missing_field(#name) })
+        } else {
+            let ident = &field.ident;
+            quote! { __default.#ident }
+        }
+    }
+
+    // Unwrap the result of a round trip, which panics if the method is infallible
+    fn unwrap(&self, description: &str, result: quote::Tokens) -> quote::Tokens {
+        if self.fallible {
+            quote! { #result? }
+        } else {
+            let message = format!("round trip of {} failed: {{}}", description);
+            quote! {
+                match #result {
+                    ::serde_roundtrip::export::Result::Ok(value) => value,
+                    ::serde_roundtrip::export::Result::Err(err) => panic!(#message, err),
+                }
+            }
+        }
+    }
 
-    // The recursive implementation of round_trip()
+    // The binding of a field in a pattern, for example ref x or x
+    fn binding(&self, ident: &Ident) -> quote::Tokens {
+        if self.by_value {
+            quote! { #ident }
+        } else {
+            quote! { ref #ident }
+        }
+    }
 
-    let round_trip = match ast.body {
+    // A reference to a field bound by a pattern, for example x or &x
+    fn reference(&self, ident: &Ident) -> quote::Tokens {
+        if self.by_value {
+            quote! { &#ident }
+        } else {
+            quote! { #ident }
+        }
+    }
+}
+
+fn field_description(field: &syn::Field) -> String {
+    match field.ident {
+        Some(ref ident) => format!("field {}", ident),
+        None => String::from("field"),
+    }
+}
+
+// The recursive implementation of a round trip method
+
+fn round_trip_body(ast: &syn::MacroInput, container: &ContainerAttrs, method: &Method) -> quote::Tokens {
+    let name = &ast.ident;
+    let constructor = container.constructor(name);
+    if container.via_serde {
+        let reference = if method.by_value { quote! { &self } } else { quote! { self } };
+        return method.unwrap(&format!("{} via serde", name), quote! { ::serde_roundtrip::via_serde::round_trip(#reference) });
+    }
+    match ast.body {
         syn::Body::Struct(syn::VariantData::Struct(ref body)) => {
-            let fields = body.iter()
-                .filter_map(|field| field.ident.as_ref())
-                .map(|ident| quote! { #ident: self.#ident.round_trip() })
+            let body = body.iter()
+                .filter(|field| !container.omits(field))
+                .filter_map(|field| field.ident.as_ref().map(|ident| (field, ident, container.target_field(field))))
                 .collect::<Vec<_>>();
-            quote! { #name { #(#fields),* } }
+            if method.by_value {
+                let idents = body.iter()
+                    .map(|&(_, ident, _)| ident)
+                    .collect::<Vec<_>>();
+                let fields = body.iter()
+                    .map(|&(field, ident, ref target_ident)| {
+                        let round_trip = method.field(container, field, quote! { #ident }, quote! { &#ident });
+                        quote! { #target_ident: #round_trip }
+                    })
+                    .chain(container.missing_fields(&constructor, method))
+                    .collect::<Vec<_>>();
+                let let_default = &container.let_default;
+                quote! { { let #name { #(#idents,)* .. } = self; #let_default #constructor { #(#fields),* } } }
+            } else {
+                let fields = body.iter()
+                    .map(|&(field, ident, ref target_ident)| {
+                        let round_trip = method.field(container, field, quote! { self.#ident }, quote! { &self.#ident });
+                        quote! { #target_ident: #round_trip }
+                    })
+                    .chain(container.missing_fields(&constructor, method))
+                    .collect::<Vec<_>>();
+                match container.default {
+                    DefaultValue::None => quote! { #constructor { #(#fields),* } },
+                    _ => {
+                        let let_default = &container.let_default;
+                        quote! { { #let_default #constructor { #(#fields),* } } }
+                    },
+                }
+            }
         },
         syn::Body::Struct(syn::VariantData::Tuple(ref body)) => {
-            let fields = (0..body.len())
-                .map(syn::Ident::from)
-                .map(|index| quote! { self.#index.round_trip() })
-                .collect::<Vec<_>>();
-            quote! { #name ( #(#fields),* ) }
+            if method.by_value {
+                let idents = (0..body.len())
+                    .map(|index| syn::Ident::from(format!("x{}", index)))
+                    .collect::<Vec<_>>();
+                let bindings = body.iter().zip(idents.iter())
+                    .map(|(field, ident)| if container.omits(field) { quote! { _ } } else { quote! { #ident } })
+                    .collect::<Vec<_>>();
+                let fields = body.iter().zip(idents.iter())
+                    .filter(|&(field, _)| !container.omits(field))
+                    .map(|(field, ident)| method.field(container, field, quote! { #ident }, quote! { &#ident }))
+                    .collect::<Vec<_>>();
+                quote! { { let #name ( #(#bindings),* ) = self; #constructor ( #(#fields),* ) } }
+            } else {
+                let fields = body.iter().enumerate()
+                    .filter(|&(_, field)| !container.omits(field))
+                    .map(|(index, field)| (field, syn::Ident::from(index)))
+                    .map(|(field, index)| method.field(container, field, quote! { self.#index }, quote! { &self.#index }))
+                    .collect::<Vec<_>>();
+                quote! { #constructor ( #(#fields),* ) }
+            }
         },
         syn::Body::Struct(syn::VariantData::Unit) => {
-            quote! { #name }
+            quote! { #constructor }
         },
         syn::Body::Enum(ref body) => {
             let cases = body.iter()
                 .map(|case| {
                     let unqualified_ident = &case.ident;
                     let ident = quote! { #name::#unqualified_ident };
+                    let target_ident = quote! { #constructor::#unqualified_ident };
                     match case.data {
                         syn::VariantData::Struct(ref body) => {
-                            let idents = body.iter()
-                                .filter_map(|field| field.ident.as_ref())
-                                .collect::<Vec<_>>();;
-                            let cloned = idents.iter()
-                                .map(|ident| quote! { #ident: #ident.round_trip() })
+                            let body = body.iter()
+                                .filter(|field| !container.omits(field))
+                                .filter_map(|field| field.ident.as_ref().map(|ident| (field, ident, container.target_field(field))))
+                                .collect::<Vec<_>>();
+                            let bindings = body.iter()
+                                .map(|&(_, ident, _)| method.binding(ident))
                                 .collect::<Vec<_>>();
-                            quote! { #ident { #(ref #idents),* } => #ident { #(#cloned),* } }
+                            let cloned = body.iter()
+                                .map(|&(field, ident, ref field_target_ident)| {
+                                    let round_trip = method.field(container, field, quote! { #ident }, method.reference(ident));
+                                    quote! { #field_target_ident: #round_trip }
+                                })
+                                .collect::<Vec<_>>();
+                            quote! { #ident { #(#bindings,)* .. } => #target_ident { #(#cloned),* } }
                         },
                         syn::VariantData::Tuple(ref body) => {
                             let idents = (0..body.len())
                                 .map(|index| syn::Ident::from(format!("x{}", index)))
                                 .collect::<Vec<_>>();
-                            let cloned = idents.iter()
-                                .map(|ident| quote! { #ident.round_trip() })
+                            let bindings = body.iter().zip(idents.iter())
+                                .map(|(field, ident)| if container.omits(field) { quote! { _ } } else { method.binding(ident) })
+                                .collect::<Vec<_>>();
+                            let cloned = body.iter().zip(idents.iter())
+                                .filter(|&(field, _)| !container.omits(field))
+                                .map(|(field, ident)| method.field(container, field, quote! { #ident }, method.reference(ident)))
                                 .collect::<Vec<_>>();
-                            quote! { #ident ( #(ref #idents),* ) => #ident ( #(#cloned),* ) }
+                            match (&container.tag, idents.first()) {
+                                // The content of a newtype variant of an internally tagged enum must have room for the tag.
+                                (&EnumTag::Internal, Some(x0)) if idents.len() == 1 => {
+                                    let reference = method.reference(x0);
+                                    let name = name.to_string();
+                                    let variant = unqualified_ident.to_string();
+                                    let check = method.unwrap(
+                                        &format!("variant {}::{}", name, variant),
+                                        quote! { ::serde_roundtrip::via_serde::check_tagged_newtype(#reference, #name, #variant) },
+                                    );
+                                    quote! { #ident ( #(#bindings),* ) => { #check; #target_ident ( #(#cloned),* ) } }
+                                },
+                                _ => quote! { #ident ( #(#bindings),* ) => #target_ident ( #(#cloned),* ) },
+                            }
                         },
                         syn::VariantData::Unit => {
-                            quote! { #ident => #ident }
+                            quote! { #ident => #target_ident }
                         },
                     }
                 })
                 .collect::<Vec<_>>();
-            quote! { match *self { #(#cases),* } }
+            if method.by_value {
+                quote! { match self { #(#cases),* } }
+            } else {
+                quote! { match *self { #(#cases),* } }
+            }
         },
+    }
+}
+
+// The implementation of round_trip_into, which updates structs field by field.
+// Fields whose round trip is affected by serde attributes are assigned rather than updated.
+
+fn round_trip_into_method(ast: &syn::MacroInput, container: &ContainerAttrs) -> quote::Tokens {
+    let name = &ast.ident;
+    if container.via_serde || container.target.is_some() {
+        return quote! {};
+    }
+    let method = Method::new("round_trip", quote! {}, false, false);
+    let update = |field: &syn::Field, ident: &Ident, tokens: quote::Tokens| {
+        let attrs = FieldAttrs::new(field);
+        if attrs.is_plain() {
+            let source = field_source(&attrs, tokens, false);
+            quote! { #source.round_trip_into(#ident); }
+        } else {
+            let round_trip = method.field(container, field, tokens.clone(), quote! { &#tokens });
+            quote! { *#ident = #round_trip; }
+        }
     };
+    let (pattern, updates) = match ast.body {
+        syn::Body::Struct(syn::VariantData::Struct(ref body)) => {
+            let idents = body.iter()
+                .filter_map(|field| field.ident.as_ref())
+                .collect::<Vec<_>>();
+            let updates = body.iter().zip(idents.iter())
+                .map(|(field, ident)| update(field, ident, quote! { self.#ident }))
+                .collect::<Vec<_>>();
+            (quote! { #name { #(ref mut #idents),* } }, updates)
+        },
+        syn::Body::Struct(syn::VariantData::Tuple(ref body)) => {
+            let idents = (0..body.len())
+                .map(|index| syn::Ident::from(format!("x{}", index)))
+                .collect::<Vec<_>>();
+            let updates = body.iter().zip(idents.iter()).enumerate()
+                .map(|(index, (field, ident))| {
+                    let index = syn::Ident::from(index);
+                    update(field, ident, quote! { self.#index })
+                })
+                .collect::<Vec<_>>();
+            (quote! { #name ( #(ref mut #idents),* ) }, updates)
+        },
+        _ => return quote! {},
+    };
+    let let_default = &container.let_default;
+    quote! {
+        fn round_trip_into(&'de self, target: &mut T) {
+            match target.as_same_mut() {
+                ::serde_roundtrip::export::Option::Some(&mut #pattern) => { #let_default #(#updates)* },
+                ::serde_roundtrip::export::Option::None => *target = self.round_trip(),
+            }
+        }
+    }
+}
+
+// The implementations of RoundTrip, TryRoundTrip, RoundTripIn and IntoRoundTrip for a source type,
+// given the generics of each implementation, and the target of RoundTripIn, which is T unless
+// the source also round trips to a tuple.
+
+struct ImplGenerics {
+    round_trip: Generics,
+    try_round_trip: Generics,
+    round_trip_in: Generics,
+    into_round_trip: Generics,
+    round_trip_in_target: quote::Tokens,
+}
+
+fn impl_round_trip_traits(ast: &syn::MacroInput, container: &ContainerAttrs, source_path: &Path, generics: &ImplGenerics) -> quote::Tokens {
+    let round_trip_generics = &generics.round_trip;
+    let round_trip_where_clause = &generics.round_trip.where_clause;
+    let round_trip = round_trip_body(ast, container, &Method::new("round_trip", quote! {}, false, false));
+    let round_trip_into = round_trip_into_method(ast, container);
 
-    // Implement RoundTrip and SameDeserialization
+    let try_round_trip_generics = &generics.try_round_trip;
+    let try_round_trip_where_clause = &generics.try_round_trip.where_clause;
+    let try_round_trip = round_trip_body(ast, container, &Method::new("try_round_trip", quote! {}, true, false));
+
+    let round_trip_in_generics = &generics.round_trip_in;
+    let round_trip_in_where_clause = &generics.round_trip_in.where_clause;
+    let round_trip_in_target = &generics.round_trip_in_target;
+    let round_trip_in = round_trip_body(ast, container, &Method::new("round_trip_in", quote! { format }, true, false));
+
+    let into_round_trip_generics = &generics.into_round_trip;
+    let into_round_trip_where_clause = &generics.into_round_trip.where_clause;
+    let into_round_trip = round_trip_body(ast, container, &Method::new("into_round_trip", quote! {}, false, true));
+
+    let round_trip_in_impl = quote! {
+        impl #round_trip_in_generics ::serde_roundtrip::RoundTripIn<'de, F, #round_trip_in_target> for #source_path
+            #round_trip_in_where_clause
+        {
+            fn round_trip_in(&'de self, format: F) -> ::serde_roundtrip::export::Result<#round_trip_in_target, ::serde_roundtrip::RoundTripError> {
+                ::serde_roundtrip::export::Result::Ok(<#round_trip_in_target as ::serde_roundtrip::SameDeserialization<'de>>::from(#round_trip_in))
+            }
+        }
+    };
+    // A field which is an option in the target only round trips in self-describing formats.
+    if container.some {
+        return round_trip_in_impl;
+    }
 
     quote! {
-        impl #all_generics ::serde_roundtrip::RoundTrip<T> for #source_path
-            #all_where_clause
+        impl #round_trip_generics ::serde_roundtrip::RoundTrip<'de, T> for #source_path
+            #round_trip_where_clause
         {
-            fn round_trip(&self) -> T { T::from(#round_trip) }
+            fn round_trip(&'de self) -> T { T::from(#round_trip) }
+            #round_trip_into
+        }
+        impl #try_round_trip_generics ::serde_roundtrip::TryRoundTrip<'de, T> for #source_path
+            #try_round_trip_where_clause
+        {
+            fn try_round_trip(&'de self) -> ::serde_roundtrip::export::Result<T, ::serde_roundtrip::RoundTripError> {
+                ::serde_roundtrip::export::Result::Ok(T::from(#try_round_trip))
+            }
         }
-        impl #target_generics ::serde_roundtrip::SameDeserialization for #target_path
-            #target_where_clause
+        #round_trip_in_impl
+        impl #into_round_trip_generics ::serde_roundtrip::IntoRoundTrip<'de, T> for #source_path
+            #into_round_trip_where_clause
+        {
+            fn into_round_trip(self) -> T { T::from(#into_round_trip) }
+        }
+    }
+}
+
+// The format type parameter F: Format of RoundTripIn, or F: SelfDescribing if the round trip
+// is only sound in self-describing formats
+
+fn format_ty_param(self_describing: bool) -> TyParam {
+    let bound = if self_describing {
+        "::serde_roundtrip::format::SelfDescribing"
+    } else {
+        "::serde_roundtrip::format::Format"
+    };
+    TyParam {
+        attrs: vec![],
+        ident: Ident::from("F"),
+        bounds: vec![ty_param_bound(bound)],
+        default: None,
+    }
+}
+
+// The name of the function which gives the default of a missing field, for example
+// __roundtrip_default_retries
+
+fn default_fn(field: &Ident) -> Ident {
+    Ident::from(format!("__roundtrip_default_{}", field))
+}
+
+// Whether a type is an Option, which deserializes as None if its field is missing
+
+fn is_option(ty: &Ty) -> bool {
+    match *ty {
+        Ty::Path(None, ref path) => path.segments.last().is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+// The target type parameter T: SameDeserialization<'de, SameAs=Foo<'b0, T0, T1>>
+
+fn same_as_ty_param(target_path: &Path) -> TyParam {
+    let bound = quote! { ::serde_roundtrip::SameDeserialization<'de, SameAs=#target_path> };
+    TyParam {
+        attrs: vec![],
+        ident: Ident::from("T"),
+        bounds: vec![ty_param_bound(bound.as_str())],
+        default: None,
+    }
+}
+
+// Round trips via serde need the data they produce to be DeserializeOwned, since it cannot
+// borrow from the content tree, for example Foo<'b0, T0, T1>: DeserializeOwned
+// for a container with #[roundtrip(via_serde)].
+
+fn via_serde_bounds(ast: &syn::MacroInput, container: &ContainerAttrs, target_path: &Path) -> Vec<syn::WherePredicate> {
+    let mut target_renaming = Renaming { original: &ast.generics, lifetime_prefix: "'b", ty_param_prefix: "T" };
+    let tys = if container.via_serde {
+        vec![Ty::Path(None, target_path.clone())]
+    } else {
+        all_fields(ast).into_iter()
+            .filter(|field| FieldAttrs::new(field).via_serde)
+            .map(|field| target_renaming.fold_ty(field.ty.clone()))
+            .collect()
+    };
+    tys.into_iter()
+        .map(|ty| where_predicate(quote! { #ty: ::serde::de::DeserializeOwned }))
+        .collect()
+}
+
+// A tuple struct with #[roundtrip(tuple)] round trips to and from the tuple of its fields,
+// in the formats which serialize them the same way. A tuple struct is serialized as a tuple
+// in JSON and bincode, but a newtype struct is serialized as its content, and an empty
+// tuple struct as an empty sequence, which are only the same as a tuple in bincode.
+// The tuple to struct round trips are only for concrete formats, since a user crate
+// cannot implement them for any F: Format.
+
+fn impl_tuple_round_trips(ast: &syn::MacroInput, de_lifetime: &LifetimeDef, source_generics: &Generics, target_generics: &Generics) -> quote::Tokens {
+    let name = &ast.ident;
+    let body = match ast.body {
+        syn::Body::Struct(syn::VariantData::Tuple(ref body)) => body,
+        _ => panic!("#[roundtrip(tuple)] is only supported for tuple structs"),
+    };
+    if body.iter().any(|field| !FieldAttrs::new(field).is_plain()) {
+        panic!("#[roundtrip(tuple)] does not support fields with serde attributes, since they change what is on the wire");
+    }
+    let formats = if body.len() < 2 { vec!["Bincode"] } else { vec!["Json", "Bincode"] };
+
+    // If the original is Foo<'l, X>(X, &'l str), the fields of the source are S0 and &'a0 str,
+    // the fields of the target are T0 and &'b0 str, and the tuple is (U0, U1).
+    let mut source_renaming = Renaming { original: &ast.generics, lifetime_prefix: "'a", ty_param_prefix: "S" };
+    let mut target_renaming = Renaming { original: &ast.generics, lifetime_prefix: "'b", ty_param_prefix: "T" };
+    let source_tys = body.iter().map(|field| source_renaming.fold_ty(field.ty.clone())).collect::<Vec<_>>();
+    let target_tys = body.iter().map(|field| target_renaming.fold_ty(field.ty.clone())).collect::<Vec<_>>();
+    let source_path = generic_path(name, source_generics);
+    let target_path = generic_path(name, target_generics);
+    let idents = (0..body.len())
+        .map(|index| Ident::from(format!("x{}", index)))
+        .collect::<Vec<_>>();
+    let indexes = (0..body.len())
+        .map(Ident::from)
+        .collect::<Vec<_>>();
+    let tuple_ty_params = (0..body.len())
+        .map(|index| TyParam {
+            attrs: vec![],
+            ident: Ident::from(format!("U{}", index)),
+            bounds: vec![ty_param_bound("::serde::Deserialize<'de>")],
+            default: None,
+        })
+        .collect::<Vec<_>>();
+    let tuple_idents = tuple_ty_params.iter()
+        .map(|ty_param| &ty_param.ident)
+        .collect::<Vec<_>>();
+
+    let (idents, indexes, tuple_idents) = (&idents, &indexes, &tuple_idents);
+    let impls = formats.into_iter().map(|format| {
+        let format = Ident::from(format);
+        let format = quote! { ::serde_roundtrip::format::#format };
+
+        // Foo<'a0, S0> round trips to (U0, U1) if S0: RoundTripIn<'de, F, U0> and &'a0 str: RoundTripIn<'de, F, U1>.
+        let mut to_tuple_generics = source_generics.clone();
+        to_tuple_generics.lifetimes.insert(0, LifetimeDef::new("'de"));
+        to_tuple_generics.ty_params.extend(tuple_ty_params.iter().cloned());
+        to_tuple_generics.where_clause.predicates.push(where_predicate(quote! { #source_path: ::serde::Serialize }));
+        to_tuple_generics.where_clause.predicates.extend(source_tys.iter().zip(tuple_idents.iter())
+            .map(|(ty, ident)| where_predicate(quote! { #ty: ::serde_roundtrip::RoundTripIn<'de, #format, #ident> })));
+        let to_tuple_where_clause = &to_tuple_generics.where_clause;
+
+        // (U0, U1) round trips to Foo<'b0, T0> if U0: RoundTripIn<'de, F, T0> and U1: RoundTripIn<'de, F, &'b0 str>.
+        let mut from_tuple_generics = target_generics.clone();
+        from_tuple_generics.lifetimes.insert(0, de_lifetime.clone());
+        from_tuple_generics.ty_params.extend(tuple_ty_params.iter().cloned());
+        from_tuple_generics.where_clause.predicates.extend(tuple_idents.iter().zip(target_tys.iter())
+            .map(|(ident, ty)| where_predicate(quote! { #ident: ::serde_roundtrip::RoundTripIn<'de, #format, #ty> })));
+        let from_tuple_where_clause = &from_tuple_generics.where_clause;
+
+        quote! {
+            impl #to_tuple_generics ::serde_roundtrip::RoundTripIn<'de, #format, ( #(#tuple_idents,)* )> for #source_path
+                #to_tuple_where_clause
+            {
+                fn round_trip_in(&'de self, format: #format) -> ::serde_roundtrip::export::Result<( #(#tuple_idents,)* ), ::serde_roundtrip::RoundTripError> {
+                    ::serde_roundtrip::export::Result::Ok(( #(::serde_roundtrip::RoundTripIn::round_trip_in(&self.#indexes, format)?,)* ))
+                }
+            }
+            impl #from_tuple_generics ::serde_roundtrip::RoundTripIn<'de, #format, #target_path> for ( #(#tuple_idents,)* )
+                #from_tuple_where_clause
+            {
+                fn round_trip_in(&'de self, format: #format) -> ::serde_roundtrip::export::Result<#target_path, ::serde_roundtrip::RoundTripError> {
+                    let ( #(ref #idents,)* ) = *self;
+                    ::serde_roundtrip::export::Result::Ok(#name( #(::serde_roundtrip::RoundTripIn::round_trip_in(#idents, format)?),* ))
+                }
+            }
+        }
+    }).collect::<Vec<_>>();
+    quote! { #(#impls)* }
+}
+
+// The hidden functions on a struct which other versions of it use to round trip, which are only
+// generated for a struct with #[roundtrip(versioned)] or #[roundtrip(from = "...")]. The defaults
+// of its fields are used by a source with #[roundtrip(missing = "...")] to initialize the fields
+// it does not have. As in serde, a missing field is initialized by its #[serde(default)],
+// or the container's, and otherwise deserializes as None if it is optional.
+// A missing field which has no default is an error, so the functions are fallible.
+// The functions are generic in '__de, since the struct may have its own lifetime 'de.
+// A struct with #[roundtrip(versioned)] also implements the hidden trait Versioned, which gives
+// the serialized names of its fields, and whether they are serialized, to a newer version
+// with #[roundtrip(from = "...")].
+
+fn impl_field_helpers(ast: &syn::MacroInput, container: &ContainerAttrs) -> quote::Tokens {
+    let body = match ast.body {
+        syn::Body::Struct(syn::VariantData::Struct(ref body)) => body,
+        _ => return quote! {},
+    };
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let fields = body.iter()
+        .filter_map(|field| field.ident.as_ref().map(|ident| (field, FieldAttrs::new(field), ident)))
+        .collect::<Vec<_>>();
+    let default_fns = fields.iter()
+        .map(|&(field, ref attrs, ident)| {
+            let ty = &field.ty;
+            let default_fn = default_fn(ident);
+            let (bound, value) = match (attrs.default.value(), container.default.value()) {
+                (Some(value), _) => (quote! {}, quote! { ::serde_roundtrip::export::Result::Ok(#value) }),
+                (None, Some(value)) => (
+                    quote! { Self: ::serde_roundtrip::export::Default, },
+                    quote! { { let __default: Self = #value; ::serde_roundtrip::export::Result::Ok(__default.#ident) } },
+                ),
+                (None, None) => {
+                    let de_name = attrs.de_name.clone().unwrap_or_else(|| ident.to_string());
+                    (
+                        quote! { #ty: ::serde::Deserialize<'__de>, },
+                        quote! { ::serde_roundtrip::via_serde::missing_field(#de_name) },
+                    )
+                },
+            };
+            quote! {
+                #[doc(hidden)]
+                pub fn #default_fn<'__de>() -> ::serde_roundtrip::export::Result<#ty, ::serde_roundtrip::RoundTripError> where
+                    #bound
+                {
+                    #value
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+    let versioned = if container.versioned {
+        let field_names = fields.iter()
+            .map(|&(_, ref attrs, ident)| {
+                let field = ident.to_string();
+                let ser_name = attrs.ser_name.clone().unwrap_or_else(|| field.clone());
+                quote! { (#field, #ser_name) }
+            })
+            .collect::<Vec<_>>();
+        let ser_names = fields.iter()
+            .filter(|&(_, attrs, _)| !attrs.skip_serializing)
+            .map(|&(_, ref attrs, ident)| attrs.ser_name.clone().unwrap_or_else(|| ident.to_string()))
+            .collect::<Vec<_>>();
+        let serializes = fields.iter()
+            .map(|&(_, ref attrs, ident)| {
+                let field = ident.to_string();
+                let serializes = match (attrs.skip_serializing, &attrs.skip_serializing_if) {
+                    (true, _) => quote! { false },
+                    (false, Some(path)) => quote! { !#path(&self.#ident) },
+                    (false, None) => quote! { true },
+                };
+                quote! { #field => #serializes, }
+            })
+            .collect::<Vec<_>>();
+        quote! {
+            impl #impl_generics ::serde_roundtrip::export::Versioned for #name #ty_generics #where_clause {
+                const FIELDS: &'static [(&'static str, &'static str)] = &[ #(#field_names),* ];
+                const NAMES: &'static [&'static str] = &[ #(#ser_names),* ];
+                fn serializes(&self, field: &str) -> bool {
+                    match field {
+                        #(#serializes)*
+                        _ => false,
+                    }
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#default_fns)*
+        }
+        #versioned
+    }
+}
+
+// A struct with #[roundtrip(from = "MsgV1")] is a new version of MsgV1, and MsgV1 round trips to it
+// in self-describing formats, which match fields by name. Each field comes from the field of MsgV1
+// with its deserialized name, or the one given by #[roundtrip(from = "...")], and a field which MsgV1 does not
+// have is marked #[roundtrip(added)]. A field of MsgV1 which is not serialized round trips as if
+// it were missing, and other fields of MsgV1 are ignored, as in serde, unless the struct has
+// #[serde(deny_unknown_fields)]. Whether the new version deserializes the serialized names of
+// the fields of MsgV1 is checked at compile time, using its implementation of Versioned.
+// A field which is added needs a default, since otherwise it could never be deserialized.
+
+fn impl_round_trip_from(ast: &syn::MacroInput, container: &ContainerAttrs, source_path: &Path) -> quote::Tokens {
+    let body = match ast.body {
+        syn::Body::Struct(syn::VariantData::Struct(ref body)) => body,
+        _ => unreachable!(),
+    };
+    let name = &ast.ident;
+    let has_default = container.default.value().is_some();
+    let mut source_constructor = source_path.clone();
+    if let Some(segment) = source_constructor.segments.last_mut() {
+        segment.parameters = PathParameters::none();
+    }
+    let source_name = source_constructor.segments.last().map(|segment| segment.ident.to_string()).unwrap_or_default();
+    let mut source_lifetimes = LifetimeCollector { lifetimes: vec![] };
+    let static_source_path = StaticLifetimes.fold_path(source_lifetimes.fold_path(source_path.clone()));
+    let source_lifetimes = source_lifetimes.lifetimes;
+    let mut source_idents: Vec<Ident> = vec![];
+    let mut checks = vec![];
+    let mut all_names = vec![];
+    let fields = body.iter().map(|field| {
+        let ident = field.ident.as_ref().unwrap();
+        let attrs = FieldAttrs::new(field);
+        let default_fn = default_fn(ident);
+        let missing = quote! { #name::#default_fn()? };
+        if attrs.deserialize_with.is_some() {
+            panic!("#[roundtrip(from = \"...\")] does not support the field {} with deserialize_with", ident);
+        }
+        if attrs.added || attrs.skip_deserializing {
+            if attrs.added && attrs.default.value().is_none() && !has_default && !is_option(&field.ty) {
+                panic!("The field {} added to {} needs a #[serde(default)] or an Option type, \
+                        since it is missing when {} is serialized", ident, name, source_name);
+            }
+            return quote! { #ident: #missing };
+        }
+        let de_name = attrs.de_name.clone().unwrap_or_else(|| ident.to_string());
+        let source_ident = attrs.from.clone().unwrap_or_else(|| Ident::from(de_name.clone()));
+        let names = Some(de_name).into_iter().chain(attrs.aliases.iter().cloned()).collect::<Vec<_>>();
+        all_names.extend(names.iter().cloned());
+        let source_field = source_ident.to_string();
+        let message = format!("The serialized name of the field {} of {} is not the name or an alias of the field {} of {}",
+                              source_ident, source_name, ident, name);
+        checks.push(quote! {
+            assert!(::serde_roundtrip::export::accepts_name(
+                ::serde_roundtrip::export::field_name(<#static_source_path as ::serde_roundtrip::export::Versioned>::FIELDS, #source_field),
+                &[ #(#names),* ],
+            ), #message);
+        });
+        let present = quote! { ::serde_roundtrip::RoundTripIn::round_trip_in(#source_ident, format)? };
+        if !source_idents.contains(&source_ident) {
+            source_idents.push(source_ident);
+        }
+        quote! { #ident: if ::serde_roundtrip::export::Versioned::serializes(self, #source_field) { #present } else { #missing } }
+    }).collect::<Vec<_>>();
+    if container.deny_unknown_fields {
+        let message = format!("{} has #[serde(deny_unknown_fields)], and does not accept a field which {} serializes",
+                              name, source_name);
+        checks.push(quote! {
+            assert!(::serde_roundtrip::export::accepts_names(
+                <#static_source_path as ::serde_roundtrip::export::Versioned>::NAMES,
+                &[ #(#all_names),* ],
+            ), #message);
+        });
+    }
+    quote! {
+        impl<'de, #(#source_lifetimes,)* F> ::serde_roundtrip::RoundTripIn<'de, F, #name> for #source_path where
+            F: ::serde_roundtrip::format::SelfDescribing,
+        {
+            fn round_trip_in(&'de self, format: F) -> ::serde_roundtrip::export::Result<#name, ::serde_roundtrip::RoundTripError> {
+                let #source_constructor { #(#source_idents,)* .. } = self;
+                ::serde_roundtrip::export::Result::Ok(#name { #(#fields),* })
+            }
+        }
+        const _: () = { #(#checks)* };
+    }
+}
+
+// Derive a RoundTrip implementation
+
+fn impl_round_trip(ast: &syn::MacroInput) -> quote::Tokens {
+    // If the original is Foo<'l, X, Y>, the target type is Foo<'b0, T0, T1>.
+    let mut target_renaming = Renaming { original: &ast.generics, lifetime_prefix: "'b", ty_param_prefix: "T" };
+    let mut target_generics = target_renaming.fold_generics(ast.generics.clone());
+    for ty_param in target_generics.ty_params.iter_mut() {
+        ty_param.bounds.push(ty_param_bound("::serde::Deserialize<'de>"));
+    }
+    for index in default_ty_params(ast) {
+        target_generics.ty_params[index].bounds.push(ty_param_bound("::serde_roundtrip::export::Default"));
+    }
+    let target_path = generic_path(&ast.ident, &target_generics);
+
+    // The serde attributes of the container, which may need a bound Foo<'b0, T0, T1>: Default.
+    let container = ContainerAttrs::new(ast, &target_path);
+    if let Some(ref target) = container.target {
+        return impl_round_trip_to_target(ast, &container, target);
+    }
+    target_generics.where_clause.predicates.extend(container.bound(&target_path));
+    let target_ty_param = same_as_ty_param(&target_path);
+
+    // If the original is Foo<'l, X, Y>, the source type is Foo<'a0, S0, S1>.
+    let mut source_renaming = Renaming { original: &ast.generics, lifetime_prefix: "'a", ty_param_prefix: "S" };
+    let source_generics = source_renaming.fold_generics(ast.generics.clone());
+    let source_path = generic_path(&ast.ident, &source_generics);
+
+    // The data being deserialized outlives the lifetimes which the target borrows from it,
+    // for example 'de: 'b0 if the original has a field of type &'l str.
+    let mut de_lifetime = LifetimeDef::new("'de");
+    de_lifetime.bounds.extend(borrowed_lifetimes(ast).into_iter()
+        .map(|index| target_generics.lifetimes[index].lifetime.clone()));
+
+    // The implementations have bounds S0: RoundTrip<'de, T0>, S1: RoundTrip<'de, T1>, or the equivalent for the other traits.
+    let format_ty_param = format_ty_param(container.only_self_describing());
+    let mut generics = ImplGenerics {
+        round_trip: impl_generics(
+            &de_lifetime,
+            &bounded_source_generics(&source_generics, &target_generics, "RoundTrip", None),
+            &target_generics,
+            &target_ty_param,
+        ),
+        try_round_trip: impl_generics(
+            &de_lifetime,
+            &bounded_source_generics(&source_generics, &target_generics, "TryRoundTrip", None),
+            &target_generics,
+            &target_ty_param,
+        ),
+        round_trip_in: impl_generics(
+            &de_lifetime,
+            &bounded_source_generics(&source_generics, &target_generics, "RoundTripIn", Some(&format_ty_param.ident)),
+            &target_generics,
+            &target_ty_param,
+        ),
+        into_round_trip: impl_generics(
+            &de_lifetime,
+            &bounded_source_generics(&source_generics, &target_generics, "IntoRoundTrip", None),
+            &target_generics,
+            &target_ty_param,
+        ),
+        round_trip_in_target: quote! { T },
+    };
+    generics.round_trip_in.ty_params.push(format_ty_param);
+    // A user crate cannot tell that a tuple does not have the same deserialization as the source,
+    // so if the source round trips to a tuple, its RoundTripIn target is the target type itself.
+    if container.tuple {
+        generics.round_trip_in.ty_params.retain(|ty_param| ty_param.ident != target_ty_param.ident);
+        generics.round_trip_in_target = quote! { #target_path };
+    }
+    source_outlives_borrowed(&mut generics.into_round_trip, &source_generics, &de_lifetime);
+    let via_serde_bounds = via_serde_bounds(ast, &container, &target_path);
+    for impl_generics in [&mut generics.round_trip, &mut generics.try_round_trip, &mut generics.round_trip_in, &mut generics.into_round_trip] {
+        impl_generics.where_clause.predicates.extend(via_serde_bounds.iter().cloned());
+    }
+    let round_trip_traits = impl_round_trip_traits(ast, &container, &source_path, &generics);
+    let tuple_round_trips = if container.tuple {
+        impl_tuple_round_trips(ast, &de_lifetime, &source_generics, &target_generics)
+    } else {
+        quote! {}
+    };
+
+    // The target has the same deserialization as itself, whenever it can be deserialized.
+    let mut same_generics = target_generics.clone();
+    same_generics.lifetimes.insert(0, LifetimeDef::new("'de"));
+    same_generics.where_clause.predicates.push(where_predicate(quote! { #target_path: ::serde::Deserialize<'de> }));
+    let same_where_clause = &same_generics.where_clause;
+
+    let field_helpers = if container.versioned || container.from.is_some() {
+        impl_field_helpers(ast, &container)
+    } else {
+        quote! {}
+    };
+    let round_trip_from = match container.from {
+        Some(ref from) => impl_round_trip_from(ast, &container, from),
+        None => quote! {},
+    };
+
+    // Implement RoundTrip, TryRoundTrip, IntoRoundTrip and SameDeserialization
+
+    quote! {
+        #round_trip_traits
+        #tuple_round_trips
+        #field_helpers
+        #round_trip_from
+        impl #same_generics ::serde_roundtrip::SameDeserialization<'de> for #target_path
+            #same_where_clause
         {
             type SameAs = Self;
             fn from(data: Self) -> Self { data }
+            fn as_same_mut(&mut self) -> ::serde_roundtrip::export::Option<&mut Self> { ::serde_roundtrip::export::Option::Some(self) }
         }
     }
 }
+
+// Derive a RoundTrip implementation for #[roundtrip(target = "Msg")], which round trips
+// to a different type. The source generics are used as declared, and the fields are
+// matched with the target's fields by name, so the impl fails to compile if they differ.
+// The target implements SameDeserialization itself, for example by deriving RoundTrip.
+// Which lifetimes of the target are borrowed is not known, so 'de outlives all of them.
+
+fn impl_round_trip_to_target(ast: &syn::MacroInput, container: &ContainerAttrs, target_path: &Path) -> quote::Tokens {
+    if ast.generics.lifetimes.iter().any(|lifetime_def| lifetime_def.lifetime.ident == "'de") {
+        panic!("derive(RoundTrip) does not support a lifetime named 'de with #[roundtrip(target = \"...\")]");
+    }
+    let source_path = generic_path(&ast.ident, &ast.generics);
+    let target_ty_param = same_as_ty_param(target_path);
+    let mut target_lifetimes = LifetimeCollector { lifetimes: vec![] };
+    target_lifetimes.fold_path(target_path.clone());
+    let mut de_lifetime = LifetimeDef::new("'de");
+    de_lifetime.bounds = target_lifetimes.lifetimes;
+    let generics = impl_generics(&de_lifetime, &ast.generics, &Generics::default(), &target_ty_param);
+    let mut round_trip_in_generics = generics.clone();
+    round_trip_in_generics.ty_params.push(format_ty_param(container.only_self_describing()));
+    let mut into_round_trip_generics = generics.clone();
+    source_outlives_borrowed(&mut into_round_trip_generics, &ast.generics, &de_lifetime);
+    let generics = ImplGenerics {
+        round_trip: generics.clone(),
+        try_round_trip: generics,
+        round_trip_in: round_trip_in_generics,
+        into_round_trip: into_round_trip_generics,
+        round_trip_in_target: quote! { T },
+    };
+    impl_round_trip_traits(ast, container, &source_path, &generics)
+}
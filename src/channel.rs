@@ -0,0 +1,180 @@
+//! Channels which only serialize when the receiver is in another process.
+//!
+//! A `RoundTripSender<T>` accepts any data of type `S: RoundTrip<'de, T>`, and a
+//! `RoundTripReceiver<T>` yields data of type `T`. If the channel is local, created by
+//! `channel()`, sending calls `round_trip()` and no serialization happens. If the
+//! channel is behind a byte stream, such as a Unix socket or pipe, sending serializes
+//! the data with a `Codec`, which specifies the format. The sender and receiver
+//! have the same types in either case, so callers do not need to know which transport is in use.
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::mpsc;
+
+use super::RoundTrip;
+use super::RoundTripError;
+use super::via_serde;
+use super::via_serde::Content;
+
+/// A format for sending data over a byte stream, for example JSON or bincode.
+pub trait Codec {
+    /// Serialize data as bytes.
+    fn encode<S: ?Sized + Serialize>(&self, data: &S) -> Result<Vec<u8>, RoundTripError>;
+    /// Deserialize data from bytes.
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, RoundTripError>;
+}
+
+/// Create a local channel, whose sender round trips data without serializing it.
+pub fn channel<T>() -> (RoundTripSender<T>, RoundTripReceiver<T>) {
+    let (sender, receiver) = mpsc::channel();
+    (RoundTripSender { inner: SenderInner::Local(sender) }, RoundTripReceiver { inner: ReceiverInner::Local(receiver) })
+}
+
+// Each message on a byte stream is its length, as a little-endian u64, followed by its bytes.
+
+fn write_message<W: ?Sized + Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    let len = bytes.len() as u64;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(bytes)?;
+    writer.flush()
+}
+
+// The length comes from the stream, so the buffer only grows as the bytes arrive,
+// rather than being allocated up front from a length which may be corrupt.
+fn read_message<R: ?Sized + Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut len = [0; 8];
+    reader.read_exact(&mut len)?;
+    let len = u64::from_le_bytes(len);
+    let mut bytes = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "message is shorter than its length"));
+    }
+    Ok(bytes)
+}
+
+fn disconnected() -> RoundTripError {
+    RoundTripError::new("channel disconnected")
+}
+
+/// The sending half of a channel.
+pub struct RoundTripSender<T> {
+    inner: SenderInner<T>,
+}
+
+enum SenderInner<T> {
+    Local(mpsc::Sender<T>),
+    Remote(Arc<RemoteSender>),
+}
+
+// The codec is only used to encode content trees, so the sender need not be generic in it.
+type Encode = Box<dyn Fn(&Content) -> Result<Vec<u8>, RoundTripError> + Send + Sync>;
+
+struct RemoteSender {
+    writer: Mutex<Box<dyn Write + Send>>,
+    encode: Encode,
+}
+
+impl<T> RoundTripSender<T> {
+    /// Create a sender which writes to a byte stream, serializing data with a codec.
+    pub fn from_writer<W, C>(writer: W, codec: C) -> RoundTripSender<T> where
+        W: 'static + Write + Send,
+        C: 'static + Codec + Send + Sync,
+    {
+        let remote = RemoteSender {
+            writer: Mutex::new(Box::new(writer)),
+            encode: Box::new(move |content| codec.encode(content)),
+        };
+        RoundTripSender { inner: SenderInner::Remote(Arc::new(remote)) }
+    }
+
+    /// Send data, which is round tripped if the receiver is local, and serialized otherwise.
+    pub fn send<'de, S>(&self, data: &'de S) -> Result<(), RoundTripError> where
+        S: ?Sized + RoundTrip<'de, T>,
+        T: Deserialize<'de>,
+    {
+        match self.inner {
+            SenderInner::Local(ref sender) => sender.send(data.round_trip()).map_err(|_| disconnected()),
+            SenderInner::Remote(ref remote) => {
+                let bytes = (remote.encode)(&via_serde::to_content(data)?)?;
+                let mut writer = remote.writer.lock().map_err(|_| disconnected())?;
+                write_message(&mut **writer, &bytes).map_err(RoundTripError::new)
+            },
+        }
+    }
+
+    /// Whether the receiver is in the same process.
+    pub fn is_local(&self) -> bool {
+        match self.inner {
+            SenderInner::Local(_) => true,
+            SenderInner::Remote(_) => false,
+        }
+    }
+}
+
+impl<T> Clone for RoundTripSender<T> {
+    fn clone(&self) -> RoundTripSender<T> {
+        let inner = match self.inner {
+            SenderInner::Local(ref sender) => SenderInner::Local(sender.clone()),
+            SenderInner::Remote(ref remote) => SenderInner::Remote(remote.clone()),
+        };
+        RoundTripSender { inner }
+    }
+}
+
+/// The receiving half of a channel.
+pub struct RoundTripReceiver<T> {
+    inner: ReceiverInner<T>,
+}
+
+type Recv<T> = Box<dyn FnMut() -> Result<T, RoundTripError> + Send>;
+
+enum ReceiverInner<T> {
+    Local(mpsc::Receiver<T>),
+    Remote(Mutex<Recv<T>>),
+}
+
+impl<T> RoundTripReceiver<T> {
+    /// Create a receiver which reads from a byte stream, deserializing data with a codec.
+    pub fn from_reader<R, C>(mut reader: R, codec: C) -> RoundTripReceiver<T> where
+        R: 'static + Read + Send,
+        C: 'static + Codec + Send,
+        T: DeserializeOwned,
+    {
+        let recv = move || match read_message(&mut reader) {
+            Ok(bytes) => codec.decode(&bytes),
+            Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => Err(disconnected()),
+            Err(err) => Err(RoundTripError::new(err)),
+        };
+        RoundTripReceiver { inner: ReceiverInner::Remote(Mutex::new(Box::new(recv))) }
+    }
+
+    /// Receive data, blocking until it is available.
+    ///
+    /// This returns an error if the sender has disconnected, or if the data
+    /// could not be deserialized.
+    pub fn recv(&self) -> Result<T, RoundTripError> {
+        match self.inner {
+            ReceiverInner::Local(ref receiver) => receiver.recv().map_err(|_| disconnected()),
+            ReceiverInner::Remote(ref recv) => (*recv.lock().map_err(|_| disconnected())?)(),
+        }
+    }
+
+    /// Whether the sender is in the same process.
+    pub fn is_local(&self) -> bool {
+        match self.inner {
+            ReceiverInner::Local(_) => true,
+            ReceiverInner::Remote(_) => false,
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! A property-based harness which checks round trips against real formats.
+//!
+//! `check_round_trip::<S, T, _>(strategy, formats)` generates many values of type `S`
+//! using a proptest `Strategy`, and checks that `round_trip()` produces the same `T`
+//! as serializing then deserializing in each of the `formats`. If a value does not,
+//! proptest shrinks it to a minimal counterexample, which is reported in the panic message.
+//! The number of values can be set with the `PROPTEST_CASES` environment variable.
+//!
+//! The check is exact, so values which a format changes, such as a `NaN` in JSON,
+//! should not be generated for it, and are checked with `RoundTripIn` instead.
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use proptest::strategy::Strategy;
+use proptest::test_runner::Config;
+use proptest::test_runner::TestCaseError;
+use proptest::test_runner::TestError;
+use proptest::test_runner::TestRunner;
+
+use alloc::vec::Vec;
+
+use core::fmt::Debug;
+
+use super::RoundTripError;
+use super::RoundTripOwned;
+
+/// A real format which round trips are checked against.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RealFormat {
+    /// JSON, using `serde_json` with its `float_roundtrip` feature, since otherwise
+    /// parsing a float may produce a neighbouring one.
+    Json,
+    /// Bincode, using `bincode` with its default options.
+    Bincode,
+}
+
+impl RealFormat {
+    /// All the real formats.
+    pub const ALL: &'static [RealFormat] = &[RealFormat::Json, RealFormat::Bincode];
+
+    /// The name of this format.
+    pub fn name(self) -> &'static str {
+        match self {
+            RealFormat::Json => "JSON",
+            RealFormat::Bincode => "bincode",
+        }
+    }
+
+    /// Serialize data as bytes in this format.
+    pub fn encode<S: ?Sized + Serialize>(self, data: &S) -> Result<Vec<u8>, RoundTripError> {
+        match self {
+            RealFormat::Json => serde_json::to_vec(data).map_err(RoundTripError::new),
+            RealFormat::Bincode => bincode::serialize(data).map_err(RoundTripError::new),
+        }
+    }
+
+    /// Deserialize data from bytes in this format.
+    pub fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, RoundTripError> {
+        match self {
+            RealFormat::Json => serde_json::from_slice(bytes).map_err(RoundTripError::new),
+            RealFormat::Bincode => bincode::deserialize(bytes).map_err(RoundTripError::new),
+        }
+    }
+
+    /// Serialize then deserialize data in this format.
+    pub fn round_trip<S, T>(self, data: &S) -> Result<T, RoundTripError> where
+        S: ?Sized + Serialize,
+        T: DeserializeOwned,
+    {
+        self.decode(&self.encode(data)?)
+    }
+}
+
+/// Check that `round_trip()` agrees with each of the formats on values generated by the strategy.
+///
+/// This panics with a minimal counterexample if it does not.
+pub fn check_round_trip<S, T, G>(strategy: G, formats: &[RealFormat]) where
+    G: Strategy<Value = S>,
+    S: Debug + RoundTripOwned<T>,
+    T: Debug + PartialEq + DeserializeOwned,
+{
+    // Failures are reported rather than saved, since there is no source file to save them next to.
+    let config = Config { failure_persistence: None, ..Config::default() };
+    let mut runner = TestRunner::new(config);
+    let result = runner.run(&strategy, |data| {
+        let round_tripped: T = data.round_trip();
+        for &format in formats {
+            let via_format: T = format.round_trip(&data)
+                .map_err(|err| TestCaseError::fail(format!("{:?} failed with {}, but round_trip() succeeded", format, err)))?;
+            if via_format != round_tripped {
+                return Err(TestCaseError::fail(format!("{:?} produced {:?}, but round_trip() produced {:?}", format, via_format, round_tripped)));
+            }
+        }
+        Ok(())
+    });
+    match result {
+        Ok(()) => (),
+        Err(TestError::Fail(reason, data)) => panic!("Round trip of {:?} does not conform: {}", data, reason),
+        Err(TestError::Abort(reason)) => panic!("Round trip conformance check aborted: {}", reason),
+    }
+}
@@ -0,0 +1,569 @@
+//! Format profiles, which specify which round trips are sound for a wire format.
+//!
+//! Compatibility between types depends on the format. For example, in a positional
+//! format such as bincode, a tuple and an array of the same length are interchangeable,
+//! and in JSON, `NaN` is serialized as `null` and integer map keys are serialized as strings.
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde::ser;
+use serde_bytes::ByteBuf;
+use serde_bytes::Bytes;
+
+use alloc::collections::BinaryHeap;
+use alloc::collections::BTreeMap;
+use alloc::collections::BTreeSet;
+use alloc::collections::LinkedList;
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use core::convert::TryFrom;
+use core::time::Duration;
+
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::collections::HashSet;
+#[cfg(feature = "std")]
+use std::ffi::CStr;
+#[cfg(feature = "std")]
+use std::ffi::CString;
+#[cfg(feature = "std")]
+use std::net::IpAddr;
+#[cfg(feature = "std")]
+use std::net::Ipv4Addr;
+#[cfg(feature = "std")]
+use std::net::Ipv6Addr;
+#[cfg(feature = "std")]
+use std::net::SocketAddr;
+#[cfg(feature = "std")]
+use std::net::SocketAddrV4;
+#[cfg(feature = "std")]
+use std::net::SocketAddrV6;
+#[cfg(feature = "std")]
+use std::path::Path;
+#[cfg(feature = "std")]
+use std::path::PathBuf;
+#[cfg(feature = "std")]
+use std::time::SystemTime;
+#[cfg(feature = "std")]
+use std::hash::BuildHasher;
+
+use super::RoundTripError;
+use super::RoundTripIn;
+
+/// A profile of a wire format, used by `RoundTripIn`.
+pub trait Format: Copy {
+    /// Whether the format serializes non-finite floats, such as `NaN`, as `null`.
+    fn non_finite_floats_are_null(self) -> bool { false }
+}
+
+/// A marker for self-describing formats, such as JSON.
+///
+/// In a self-describing format, an option can be deserialized from a present value,
+/// a vector can be deserialized from an array, a string can be deserialized from a char,
+/// and a number can be deserialized as any numeric type which can hold its value.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a self-describing format profile",
+    note = "a derived type which contains a type that only round trips in self-describing formats, \
+            such as an internally tagged enum, needs a #[roundtrip(self_describing)] attribute",
+)]
+pub trait SelfDescribing: Format {}
+
+/// A marker for positional formats, such as bincode.
+///
+/// In a positional format, a tuple and an array of the same length are interchangeable.
+pub trait Positional: Format {}
+
+/// A round trip in a format which cannot fail, such as widening an integer.
+///
+/// If `S: WidenIn<F, T>` then `data.widen_in(format)` produces the same result as
+/// `data.round_trip_in(format)`, which always succeeds. Round trips which may fail,
+/// such as narrowing an integer, only implement `RoundTripIn`.
+pub trait WidenIn<F: Format, Target> {
+    /// This function specifies the behaviour of an infallible round-trip in a format.
+    fn widen_in(&self, format: F) -> Target;
+}
+
+/// The profile which makes no assumptions about the format.
+///
+/// This only allows the round trips which are sound in any format
+/// which represents its data faithfully, which are the round trips of `TryRoundTrip`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AnyFormat;
+
+impl Format for AnyFormat {}
+
+/// The profile of self-describing formats, such as CBOR or MessagePack.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AnySelfDescribing;
+
+impl Format for AnySelfDescribing {}
+
+impl SelfDescribing for AnySelfDescribing {}
+
+/// The profile of JSON.
+///
+/// This assumes that serde_json parses floats exactly, which needs its `float_roundtrip` feature,
+/// since otherwise parsing a float may produce a neighbouring one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Json;
+
+impl Format for Json {
+    fn non_finite_floats_are_null(self) -> bool { true }
+}
+
+impl SelfDescribing for Json {}
+
+/// The profile of bincode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Bincode;
+
+impl Format for Bincode {}
+
+impl Positional for Bincode {}
+
+// Present values, which can be deserialized as options in self-describing formats
+
+macro_rules! some_impls {
+    (impl<$($g:ident),*> $s:ty) => {
+        impl<'de,$($g,)*T0,F> RoundTripIn<'de, F, Option<T0>> for $s where
+            F: SelfDescribing,
+            $s: RoundTripIn<'de, F, T0>,
+            T0: Deserialize<'de>,
+        {
+            fn round_trip_in(&'de self, format: F) -> Result<Option<T0>, RoundTripError> {
+                <$s as RoundTripIn<'de, F, T0>>::round_trip_in(self, format).map(Some)
+            }
+        }
+    };
+    ($s:ty) => {
+        some_impls!(impl<> $s);
+    };
+}
+
+some_impls!(ByteBuf);
+some_impls!(Bytes);
+#[cfg(feature = "std")]
+some_impls!(CStr);
+#[cfg(feature = "std")]
+some_impls!(CString);
+some_impls!(Duration);
+#[cfg(feature = "std")]
+some_impls!(IpAddr);
+#[cfg(feature = "std")]
+some_impls!(Ipv4Addr);
+#[cfg(feature = "std")]
+some_impls!(Ipv6Addr);
+#[cfg(feature = "std")]
+some_impls!(Path);
+#[cfg(feature = "std")]
+some_impls!(PathBuf);
+#[cfg(feature = "std")]
+some_impls!(SocketAddr);
+#[cfg(feature = "std")]
+some_impls!(SocketAddrV4);
+#[cfg(feature = "std")]
+some_impls!(SocketAddrV6);
+some_impls!(String);
+#[cfg(feature = "std")]
+some_impls!(SystemTime);
+some_impls!(bool);
+some_impls!(char);
+some_impls!(i128);
+some_impls!(i16);
+some_impls!(i32);
+some_impls!(i64);
+some_impls!(i8);
+some_impls!(isize);
+some_impls!(str);
+some_impls!(u128);
+some_impls!(u16);
+some_impls!(u32);
+some_impls!(u64);
+some_impls!(u8);
+some_impls!(usize);
+
+some_impls!(impl<S0> [S0]);
+some_impls!(impl<S0> BinaryHeap<S0>);
+some_impls!(impl<S0,S1> BTreeMap<S0,S1>);
+some_impls!(impl<S0> BTreeSet<S0>);
+#[cfg(feature = "std")]
+some_impls!(impl<S0,S1,G> HashMap<S0,S1,G>);
+#[cfg(feature = "std")]
+some_impls!(impl<S0,G> HashSet<S0,G>);
+some_impls!(impl<S0> LinkedList<S0>);
+some_impls!(impl<S0> Vec<S0>);
+some_impls!(impl<S0> VecDeque<S0>);
+
+some_impls!(impl<S0> (S0,));
+some_impls!(impl<S0,S1> (S0,S1));
+some_impls!(impl<S0,S1,S2> (S0,S1,S2));
+some_impls!(impl<S0,S1,S2,S3> (S0,S1,S2,S3));
+some_impls!(impl<S0,S1,S2,S3,S4> (S0,S1,S2,S3,S4));
+some_impls!(impl<S0,S1,S2,S3,S4,S5> (S0,S1,S2,S3,S4,S5));
+some_impls!(impl<S0,S1,S2,S3,S4,S5,S6> (S0,S1,S2,S3,S4,S5,S6));
+some_impls!(impl<S0,S1,S2,S3,S4,S5,S6,S7> (S0,S1,S2,S3,S4,S5,S6,S7));
+some_impls!(impl<S0,S1,S2,S3,S4,S5,S6,S7,S8> (S0,S1,S2,S3,S4,S5,S6,S7,S8));
+some_impls!(impl<S0,S1,S2,S3,S4,S5,S6,S7,S8,S9> (S0,S1,S2,S3,S4,S5,S6,S7,S8,S9));
+some_impls!(impl<S0,S1,S2,S3,S4,S5,S6,S7,S8,S9,SA> (S0,S1,S2,S3,S4,S5,S6,S7,S8,S9,SA));
+some_impls!(impl<S0,S1,S2,S3,S4,S5,S6,S7,S8,S9,SA,SB> (S0,S1,S2,S3,S4,S5,S6,S7,S8,S9,SA,SB));
+some_impls!(impl<S0,S1,S2,S3,S4,S5,S6,S7,S8,S9,SA,SB,SC> (S0,S1,S2,S3,S4,S5,S6,S7,S8,S9,SA,SB,SC));
+some_impls!(impl<S0,S1,S2,S3,S4,S5,S6,S7,S8,S9,SA,SB,SC,SD> (S0,S1,S2,S3,S4,S5,S6,S7,S8,S9,SA,SB,SC,SD));
+some_impls!(impl<S0,S1,S2,S3,S4,S5,S6,S7,S8,S9,SA,SB,SC,SD,SE> (S0,S1,S2,S3,S4,S5,S6,S7,S8,S9,SA,SB,SC,SD,SE));
+some_impls!(impl<S0,S1,S2,S3,S4,S5,S6,S7,S8,S9,SA,SB,SC,SD,SE,SF> (S0,S1,S2,S3,S4,S5,S6,S7,S8,S9,SA,SB,SC,SD,SE,SF));
+
+impl<'de,S,T0,F,const N: usize> RoundTripIn<'de, F, Option<T0>> for [S; N] where
+    F: SelfDescribing,
+    [S; N]: RoundTripIn<'de, F, T0>,
+    T0: Deserialize<'de>,
+{
+    fn round_trip_in(&'de self, format: F) -> Result<Option<T0>, RoundTripError> {
+        <[S; N] as RoundTripIn<'de, F, T0>>::round_trip_in(self, format).map(Some)
+    }
+}
+
+// Floats, which are deserialized as `None` in formats which serialize non-finite floats as null
+
+macro_rules! float_option_impls {
+    ($t:ty) => {
+        impl<'de,T0,F> RoundTripIn<'de, F, Option<T0>> for $t where
+            F: SelfDescribing,
+            $t: RoundTripIn<'de, F, T0>,
+            T0: Deserialize<'de>,
+        {
+            fn round_trip_in(&'de self, format: F) -> Result<Option<T0>, RoundTripError> {
+                if format.non_finite_floats_are_null() && !self.is_finite() {
+                    Ok(None)
+                } else {
+                    <$t as RoundTripIn<'de, F, T0>>::round_trip_in(self, format).map(Some)
+                }
+            }
+        }
+    };
+}
+
+float_option_impls!(f32);
+float_option_impls!(f64);
+
+// Values which a format serializes as null, such as `()`, `None` and unit structs,
+// which are deserialized as `None` rather than `Some` in self-describing formats.
+// A value is checked by serializing it with a serializer which fails at anything else.
+
+pub(crate) fn serializes_as_null<S: ?Sized + Serialize, F: Format>(data: &S, format: F) -> bool {
+    data.serialize(NullChecker(format)).is_ok()
+}
+
+#[derive(Clone, Copy, Debug)]
+struct NullChecker<F>(F);
+
+impl<F> NullChecker<F> {
+    fn not_null(self) -> RoundTripError {
+        RoundTripError::new("not serialized as null")
+    }
+}
+
+impl<F: Format> ser::Serializer for NullChecker<F> {
+    type Ok = ();
+    type Error = RoundTripError;
+    type SerializeSeq = ser::Impossible<(), RoundTripError>;
+    type SerializeTuple = ser::Impossible<(), RoundTripError>;
+    type SerializeTupleStruct = ser::Impossible<(), RoundTripError>;
+    type SerializeTupleVariant = ser::Impossible<(), RoundTripError>;
+    type SerializeMap = ser::Impossible<(), RoundTripError>;
+    type SerializeStruct = ser::Impossible<(), RoundTripError>;
+    type SerializeStructVariant = ser::Impossible<(), RoundTripError>;
+
+    fn serialize_bool(self, _: bool) -> Result<(), RoundTripError> { Err(self.not_null()) }
+    fn serialize_i8(self, _: i8) -> Result<(), RoundTripError> { Err(self.not_null()) }
+    fn serialize_i16(self, _: i16) -> Result<(), RoundTripError> { Err(self.not_null()) }
+    fn serialize_i32(self, _: i32) -> Result<(), RoundTripError> { Err(self.not_null()) }
+    fn serialize_i64(self, _: i64) -> Result<(), RoundTripError> { Err(self.not_null()) }
+    fn serialize_i128(self, _: i128) -> Result<(), RoundTripError> { Err(self.not_null()) }
+    fn serialize_u8(self, _: u8) -> Result<(), RoundTripError> { Err(self.not_null()) }
+    fn serialize_u16(self, _: u16) -> Result<(), RoundTripError> { Err(self.not_null()) }
+    fn serialize_u32(self, _: u32) -> Result<(), RoundTripError> { Err(self.not_null()) }
+    fn serialize_u64(self, _: u64) -> Result<(), RoundTripError> { Err(self.not_null()) }
+    fn serialize_u128(self, _: u128) -> Result<(), RoundTripError> { Err(self.not_null()) }
+    fn serialize_f32(self, v: f32) -> Result<(), RoundTripError> {
+        if self.0.non_finite_floats_are_null() && !v.is_finite() { Ok(()) } else { Err(self.not_null()) }
+    }
+    fn serialize_f64(self, v: f64) -> Result<(), RoundTripError> {
+        if self.0.non_finite_floats_are_null() && !v.is_finite() { Ok(()) } else { Err(self.not_null()) }
+    }
+    fn serialize_char(self, _: char) -> Result<(), RoundTripError> { Err(self.not_null()) }
+    fn serialize_str(self, _: &str) -> Result<(), RoundTripError> { Err(self.not_null()) }
+    fn serialize_bytes(self, _: &[u8]) -> Result<(), RoundTripError> { Err(self.not_null()) }
+    fn serialize_none(self) -> Result<(), RoundTripError> { Ok(()) }
+    fn serialize_some<S: ?Sized + Serialize>(self, value: &S) -> Result<(), RoundTripError> { value.serialize(self) }
+    fn serialize_unit(self) -> Result<(), RoundTripError> { Ok(()) }
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), RoundTripError> { Ok(()) }
+    fn serialize_unit_variant(self, _: &'static str, _: u32, _: &'static str) -> Result<(), RoundTripError> { Err(self.not_null()) }
+    fn serialize_newtype_struct<S: ?Sized + Serialize>(self, _: &'static str, value: &S) -> Result<(), RoundTripError> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<S: ?Sized + Serialize>(self, _: &'static str, _: u32, _: &'static str, _: &S) -> Result<(), RoundTripError> {
+        Err(self.not_null())
+    }
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, RoundTripError> { Err(self.not_null()) }
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, RoundTripError> { Err(self.not_null()) }
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeTupleStruct, RoundTripError> {
+        Err(self.not_null())
+    }
+    fn serialize_tuple_variant(self, _: &'static str, _: u32, _: &'static str, _: usize) -> Result<Self::SerializeTupleVariant, RoundTripError> {
+        Err(self.not_null())
+    }
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, RoundTripError> { Err(self.not_null()) }
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeStruct, RoundTripError> { Err(self.not_null()) }
+    fn serialize_struct_variant(self, _: &'static str, _: u32, _: &'static str, _: usize) -> Result<Self::SerializeStructVariant, RoundTripError> {
+        Err(self.not_null())
+    }
+}
+
+// Tuples and arrays, which are interchangeable in positional formats
+
+macro_rules! positional_tuple_impls {
+    ($len:expr; $($xs:ident : $Ss:ident => $Ts:ident),*) => {
+        impl<'de,$($Ss),*,T,F> RoundTripIn<'de, F, [T; $len]> for ($($Ss,)*) where
+            F: Positional,
+            $($Ss: RoundTripIn<'de, F, T>),*,
+            T: Deserialize<'de>,
+        {
+            fn round_trip_in(&'de self, format: F) -> Result<[T; $len], RoundTripError> {
+                let ($(ref $xs,)*) = *self;
+                Ok([$($xs.round_trip_in(format)?),*])
+            }
+        }
+        impl<'de,S,$($Ts),*,F> RoundTripIn<'de, F, ($($Ts,)*)> for [S; $len] where
+            F: Positional,
+            $(S: RoundTripIn<'de, F, $Ts>),*,
+            $($Ts: Deserialize<'de>),*,
+        {
+            fn round_trip_in(&'de self, format: F) -> Result<($($Ts,)*), RoundTripError> {
+                let [$(ref $xs),*] = *self;
+                Ok(($($xs.round_trip_in(format)?,)*))
+            }
+        }
+    };
+}
+
+positional_tuple_impls!(1; x_0: S0 => T0);
+
+positional_tuple_impls!(2; x_0: S0 => T0, x_1: S1 => T1);
+
+positional_tuple_impls!(3; x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2);
+
+positional_tuple_impls!(4; x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3);
+
+positional_tuple_impls!(5; x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+                        x_4: S4 => T4);
+
+positional_tuple_impls!(6; x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+                        x_4: S4 => T4, x_5: S5 => T5);
+
+positional_tuple_impls!(7; x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+                        x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6);
+
+positional_tuple_impls!(8; x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+                        x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7);
+
+positional_tuple_impls!(9; x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+                        x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
+                        x_8: S8 => T8);
+
+positional_tuple_impls!(10; x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+                        x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
+                        x_8: S8 => T8, x_9: S9 => T9);
+
+positional_tuple_impls!(11; x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+                        x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
+                        x_8: S8 => T8, x_9: S9 => T9, x_a: SA => TA);
+
+positional_tuple_impls!(12; x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+                        x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
+                        x_8: S8 => T8, x_9: S9 => T9, x_a: SA => TA, x_b: SB => TB);
+
+positional_tuple_impls!(13; x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+                        x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
+                        x_8: S8 => T8, x_9: S9 => T9, x_a: SA => TA, x_b: SB => TB,
+                        x_c: SC => TC);
+
+positional_tuple_impls!(14; x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+                        x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
+                        x_8: S8 => T8, x_9: S9 => T9, x_a: SA => TA, x_b: SB => TB,
+                        x_c: SC => TC, x_d: SD => TD);
+
+positional_tuple_impls!(15; x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+                        x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
+                        x_8: S8 => T8, x_9: S9 => T9, x_a: SA => TA, x_b: SB => TB,
+                        x_c: SC => TC, x_d: SD => TD, x_e: SE => TE);
+
+positional_tuple_impls!(16; x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+                        x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
+                        x_8: S8 => T8, x_9: S9 => T9, x_a: SA => TA, x_b: SB => TB,
+                        x_c: SC => TC, x_d: SD => TD, x_e: SE => TE, x_f: SF => TF);
+
+// Chars, which are serialized as strings in self-describing formats
+
+impl<'de,F> RoundTripIn<'de, F, String> for char where
+    F: SelfDescribing,
+{
+    fn round_trip_in(&'de self, _: F) -> Result<String, RoundTripError> { Ok(self.to_string()) }
+}
+
+// Integers, which can be deserialized as a different integer type in self-describing formats.
+// Widening to a type which can hold every value never fails, so it also implements `WidenIn`.
+// Narrowing fails if the value is out of range, as in serde.
+
+macro_rules! widening_impls {
+    ($s:ident => $($t:ident),*) => {
+        $(
+            impl<F> WidenIn<F, $t> for $s where
+                F: SelfDescribing,
+            {
+                fn widen_in(&self, _: F) -> $t { $t::from(*self) }
+            }
+            impl<'de,F> RoundTripIn<'de, F, $t> for $s where
+                F: SelfDescribing,
+            {
+                fn round_trip_in(&'de self, format: F) -> Result<$t, RoundTripError> { Ok(self.widen_in(format)) }
+            }
+        )*
+    };
+}
+
+macro_rules! narrowing_impls {
+    ($s:ident => $($t:ident),*) => {
+        $(
+            impl<'de,F> RoundTripIn<'de, F, $t> for $s where
+                F: SelfDescribing,
+            {
+                fn round_trip_in(&'de self, _: F) -> Result<$t, RoundTripError> {
+                    $t::try_from(*self).map_err(|_| RoundTripError::new(format_args!(
+                        "invalid value: integer `{}`, expected {}", self, stringify!($t)
+                    )))
+                }
+            }
+        )*
+    };
+}
+
+widening_impls!(i16 => i128, i32, i64, isize);
+widening_impls!(i32 => i128, i64);
+widening_impls!(i64 => i128);
+widening_impls!(i8 => i128, i16, i32, i64, isize);
+widening_impls!(u16 => i128, i32, i64, u128, u32, u64, usize);
+widening_impls!(u32 => i128, i64, u128, u64);
+widening_impls!(u64 => i128, u128);
+widening_impls!(u8 => i128, i16, i32, i64, isize, u128, u16, u32, u64, usize);
+
+narrowing_impls!(i128 => i16, i32, i64, i8, isize, u128, u16, u32, u64, u8, usize);
+narrowing_impls!(i16 => i8, u128, u16, u32, u64, u8, usize);
+narrowing_impls!(i32 => i16, i8, isize, u128, u16, u32, u64, u8, usize);
+narrowing_impls!(i64 => i16, i32, i8, isize, u128, u16, u32, u64, u8, usize);
+narrowing_impls!(i8 => u128, u16, u32, u64, u8, usize);
+narrowing_impls!(isize => i128, i16, i32, i64, i8, u128, u16, u32, u64, u8, usize);
+narrowing_impls!(u128 => i128, i16, i32, i64, i8, isize, u16, u32, u64, u8, usize);
+narrowing_impls!(u16 => i16, i8, isize, u8);
+narrowing_impls!(u32 => i16, i32, i8, isize, u16, u8, usize);
+narrowing_impls!(u64 => i16, i32, i64, i8, isize, u16, u32, u8, usize);
+narrowing_impls!(u8 => i8);
+narrowing_impls!(usize => i128, i16, i32, i64, i8, isize, u128, u16, u32, u64, u8);
+
+// Integers, which can be deserialized as floats in self-describing formats.
+// Serde converts any integer to a float, but these are the conversions which are lossless.
+
+widening_impls!(i16 => f32, f64);
+widening_impls!(i32 => f64);
+widening_impls!(i8 => f32, f64);
+widening_impls!(u16 => f32, f64);
+widening_impls!(u32 => f64);
+widening_impls!(u8 => f32, f64);
+
+// Single precision floats, which are serialized as their shortest decimal representation in JSON,
+// so they are deserialized as the closest double to that decimal, and as themselves in binary
+// self-describing formats. As in JSON, a non-finite float fails to deserialize.
+// The decimal is formatted by ryu, as serde_json does, since when two decimals are equally short
+// and close, it may choose a different one from Display.
+
+impl<'de> RoundTripIn<'de, Json, f64> for f32 {
+    fn round_trip_in(&'de self, _: Json) -> Result<f64, RoundTripError> {
+        if self.is_finite() {
+            ryu::Buffer::new().format_finite(*self).parse().map_err(RoundTripError::new)
+        } else {
+            Err(RoundTripError::new("invalid type: null, expected f64"))
+        }
+    }
+}
+
+impl<'de> RoundTripIn<'de, AnySelfDescribing, f64> for f32 {
+    fn round_trip_in(&'de self, _: AnySelfDescribing) -> Result<f64, RoundTripError> { Ok(f64::from(*self)) }
+}
+
+// Byte buffers, which are serialized as sequences of bytes in JSON and bincode,
+// but not in formats with a separate representation of bytes, such as CBOR
+
+macro_rules! bytes_as_sequence_impls {
+    ($f:ty) => {
+        impl<'de> RoundTripIn<'de, $f, Vec<u8>> for ByteBuf {
+            fn round_trip_in(&'de self, _: $f) -> Result<Vec<u8>, RoundTripError> { Ok(self.to_vec()) }
+        }
+        impl<'de> RoundTripIn<'de, $f, Vec<u8>> for Bytes {
+            fn round_trip_in(&'de self, _: $f) -> Result<Vec<u8>, RoundTripError> { Ok(self.to_vec()) }
+        }
+    };
+}
+
+bytes_as_sequence_impls!(Bincode);
+bytes_as_sequence_impls!(Json);
+
+// Arrays, which are serialized as sequences in self-describing formats
+
+impl<'de,S,T,F,const N: usize> RoundTripIn<'de, F, Vec<T>> for [S; N] where
+    [S; N]: Serialize,
+    F: SelfDescribing,
+    S: RoundTripIn<'de, F, T>,
+    T: Deserialize<'de>,
+{
+    fn round_trip_in(&'de self, format: F) -> Result<Vec<T>, RoundTripError> {
+        self.iter().map(|x| x.round_trip_in(format)).collect()
+    }
+}
+
+// Maps with integer keys, whose keys are serialized as strings in JSON
+
+macro_rules! json_integer_key_impls {
+    ($k:ty) => {
+        impl<'de,S1,T1> RoundTripIn<'de, Json, BTreeMap<String,T1>> for BTreeMap<$k,S1> where
+            S1: RoundTripIn<'de, Json, T1>,
+            T1: Deserialize<'de>,
+        {
+            fn round_trip_in(&'de self, format: Json) -> Result<BTreeMap<String,T1>, RoundTripError> {
+                self.iter().map(|(x1,x2)| Ok((x1.to_string(), x2.round_trip_in(format)?))).collect()
+            }
+        }
+        #[cfg(feature = "std")]
+        impl<'de,S1,T1,G,H> RoundTripIn<'de, Json, HashMap<String,T1,H>> for HashMap<$k,S1,G> where
+            S1: RoundTripIn<'de, Json, T1>,
+            T1: Deserialize<'de>,
+            H: BuildHasher+Default,
+        {
+            fn round_trip_in(&'de self, format: Json) -> Result<HashMap<String,T1,H>, RoundTripError> {
+                self.iter().map(|(x1,x2)| Ok((x1.to_string(), x2.round_trip_in(format)?))).collect()
+            }
+        }
+    };
+}
+
+json_integer_key_impls!(i16);
+json_integer_key_impls!(i32);
+json_integer_key_impls!(i64);
+json_integer_key_impls!(i8);
+json_integer_key_impls!(isize);
+json_integer_key_impls!(u16);
+json_integer_key_impls!(u32);
+json_integer_key_impls!(u64);
+json_integer_key_impls!(u8);
+json_integer_key_impls!(usize);
@@ -0,0 +1,96 @@
+//! Entry points for fuzzing round trips, for example with `cargo fuzz`.
+//!
+//! `check_bytes::<S, T>(bytes, format)` deserializes data of type `S` from arbitrary bytes
+//! in a real format, and checks that `round_trip()` produces the same `T` as serializing
+//! then deserializing the data in that format. Bytes which do not deserialize as `S` are ignored.
+//! `check_bytes_in::<S, T, _>(bytes, format, profile)` checks `round_trip_in(profile)` instead,
+//! for round trips which depend on the format, such as from a `u8` to an `i64` in JSON.
+//! With the `arbitrary` feature, `check_arbitrary::<S, T>(bytes, formats)` instead builds
+//! the data from the bytes using `arbitrary::Arbitrary`, which reaches more values of `S`.
+//! In either case a round trip which disagrees with the format panics,
+//! with the path to the first difference, which the fuzzer reports as a crash.
+//!
+//! A result which is not equal to itself, for example because it contains a `NaN`,
+//! cannot be compared, so its data is ignored.
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+#[cfg(feature = "arbitrary")]
+use arbitrary::Arbitrary;
+#[cfg(feature = "arbitrary")]
+use arbitrary::Unstructured;
+
+use core::fmt::Debug;
+
+use super::RoundTripIn;
+use super::RoundTripOwned;
+use super::checked_round_trip_with;
+use super::conformance::RealFormat;
+use super::differs;
+use super::format::Format;
+
+/// Check the round trip of data deserialized from arbitrary bytes in a real format.
+pub fn check_bytes<S, T>(bytes: &[u8], format: RealFormat) where
+    S: DeserializeOwned + RoundTripOwned<T>,
+    T: Debug + PartialEq + Serialize + DeserializeOwned,
+{
+    if let Ok(data) = format.decode::<S>(bytes) {
+        check(&data, format);
+    }
+}
+
+/// Check the round trip in a format profile of data deserialized from arbitrary bytes in a real format.
+///
+/// The profile should be the one for the real format, for example `format::Json` for `RealFormat::Json`.
+/// If the round trip fails, serializing then deserializing should also fail.
+pub fn check_bytes_in<S, T, F>(bytes: &[u8], format: RealFormat, profile: F) where
+    S: DeserializeOwned + for<'de> RoundTripIn<'de, F, T>,
+    T: Debug + PartialEq + Serialize + DeserializeOwned,
+    F: Format,
+{
+    let data = match format.decode::<S>(bytes) {
+        Ok(data) => data,
+        Err(_) => return,
+    };
+    match (data.round_trip_in(profile), format.round_trip::<S, T>(&data)) {
+        (Ok(result), Ok(expected)) => if is_reflexive(&expected) && result != expected {
+            differs(format.name(), &result, &expected);
+        },
+        (Err(_), Err(_)) => (),
+        (Ok(_), Err(err)) => panic!("round trip succeeded but {} failed: {}", format.name(), err),
+        (Err(err), Ok(_)) => panic!("round trip failed with {}, but {} succeeded", err, format.name()),
+    }
+}
+
+/// Check the round trip of data built from arbitrary bytes, in each of the real formats.
+#[cfg(feature = "arbitrary")]
+pub fn check_arbitrary<'a, S, T>(bytes: &'a [u8], formats: &[RealFormat]) where
+    S: Arbitrary<'a> + RoundTripOwned<T>,
+    T: Debug + PartialEq + Serialize + DeserializeOwned,
+{
+    if let Ok(data) = S::arbitrary_take_rest(Unstructured::new(bytes)) {
+        for &format in formats {
+            check(&data, format);
+        }
+    }
+}
+
+fn check<S, T>(data: &S, format: RealFormat) where
+    S: RoundTripOwned<T>,
+    T: Debug + PartialEq + Serialize + DeserializeOwned,
+{
+    let expected = format.round_trip::<S, T>(data);
+    if let Ok(ref expected) = expected {
+        if !is_reflexive(expected) {
+            return;
+        }
+    }
+    let _: T = checked_round_trip_with(data, format.name(), |_| expected);
+}
+
+// Whether data is equal to itself, which PartialEq does not require, for example of a NaN.
+// Data which is not equal to itself cannot be compared with the result of a round trip.
+fn is_reflexive<T: PartialEq>(data: &T) -> bool {
+    data.eq(data)
+}
@@ -1,102 +1,563 @@
 //! Enable short-circuiting a serialization-then-deserialization roundtrip.
 
+#![no_std]
+
+#[macro_use]
+extern crate alloc;
+#[cfg(feature = "arbitrary")]
+extern crate arbitrary;
+#[cfg(feature = "conformance")]
+extern crate bincode;
+#[cfg(feature = "indexmap")]
+extern crate indexmap;
+#[cfg(feature = "conformance")]
+extern crate proptest;
+extern crate ryu;
 extern crate serde;
+extern crate serde_bytes;
+#[cfg(feature = "conformance")]
+extern crate serde_json;
+#[cfg(feature = "std")]
+extern crate std;
 
 use serde::Deserialize;
 use serde::Serialize;
-use serde::bytes::ByteBuf;
-use serde::bytes::Bytes;
-
-use std::borrow::Cow;
-use std::borrow::ToOwned;
-use std::collections::BinaryHeap;
-use std::collections::BTreeMap;
-use std::collections::BTreeSet;
+use serde::de::DeserializeOwned;
+use serde_bytes::ByteBuf;
+use serde_bytes::Bytes;
+
+use alloc::borrow::Cow;
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::collections::BinaryHeap;
+use alloc::collections::BTreeMap;
+use alloc::collections::BTreeSet;
+use alloc::collections::LinkedList;
+use alloc::collections::VecDeque;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use core::any::type_name;
+use core::borrow::Borrow;
+use core::cell::Cell;
+use core::cell::Ref;
+use core::cell::RefCell;
+use core::cmp::Reverse;
+use core::fmt;
+use core::fmt::Debug;
+use core::fmt::Display;
+#[cfg(any(feature = "std", feature = "indexmap"))]
+use core::hash::BuildHasher;
+#[cfg(any(feature = "std", feature = "indexmap"))]
+use core::hash::Hash;
+use core::marker::PhantomData;
+use core::num::NonZeroI128;
+use core::num::NonZeroI16;
+use core::num::NonZeroI32;
+use core::num::NonZeroI64;
+use core::num::NonZeroI8;
+use core::num::NonZeroIsize;
+use core::num::NonZeroU128;
+use core::num::NonZeroU16;
+use core::num::NonZeroU32;
+use core::num::NonZeroU64;
+use core::num::NonZeroU8;
+use core::num::NonZeroUsize;
+use core::num::Saturating;
+use core::num::Wrapping;
+use core::ops::Bound;
+use core::ops::Deref;
+use core::ops::Range;
+use core::ops::RangeFrom;
+use core::ops::RangeInclusive;
+use core::ops::RangeTo;
+#[cfg(all(feature = "std", target_has_atomic = "8"))]
+use core::sync::atomic::AtomicBool;
+#[cfg(all(feature = "std", target_has_atomic = "16"))]
+use core::sync::atomic::AtomicI16;
+#[cfg(all(feature = "std", target_has_atomic = "32"))]
+use core::sync::atomic::AtomicI32;
+#[cfg(all(feature = "std", target_has_atomic = "64"))]
+use core::sync::atomic::AtomicI64;
+#[cfg(all(feature = "std", target_has_atomic = "8"))]
+use core::sync::atomic::AtomicI8;
+#[cfg(all(feature = "std", target_has_atomic = "ptr"))]
+use core::sync::atomic::AtomicIsize;
+#[cfg(all(feature = "std", target_has_atomic = "16"))]
+use core::sync::atomic::AtomicU16;
+#[cfg(all(feature = "std", target_has_atomic = "32"))]
+use core::sync::atomic::AtomicU32;
+#[cfg(all(feature = "std", target_has_atomic = "64"))]
+use core::sync::atomic::AtomicU64;
+#[cfg(all(feature = "std", target_has_atomic = "8"))]
+use core::sync::atomic::AtomicU8;
+#[cfg(all(feature = "std", target_has_atomic = "ptr"))]
+use core::sync::atomic::AtomicUsize;
+#[cfg(feature = "std")]
+use core::sync::atomic::Ordering;
+use core::time::Duration;
+
+#[cfg(feature = "std")]
 use std::collections::HashMap;
+#[cfg(feature = "std")]
 use std::collections::HashSet;
-use std::collections::LinkedList;
-use std::collections::VecDeque;
+#[cfg(feature = "std")]
+use std::error::Error;
+#[cfg(feature = "std")]
 use std::ffi::CStr;
+#[cfg(feature = "std")]
 use std::ffi::CString;
-use std::hash::BuildHasher;
-use std::hash::Hash;
-use std::marker::PhantomData;
+#[cfg(all(feature = "std", any(unix, windows)))]
+use std::ffi::OsString;
+#[cfg(feature = "std")]
 use std::net::IpAddr;
+#[cfg(feature = "std")]
 use std::net::Ipv4Addr;
+#[cfg(feature = "std")]
 use std::net::Ipv6Addr;
+#[cfg(feature = "std")]
 use std::net::SocketAddr;
+#[cfg(feature = "std")]
 use std::net::SocketAddrV4;
+#[cfg(feature = "std")]
 use std::net::SocketAddrV6;
-use std::ops::Deref;
+#[cfg(feature = "std")]
 use std::path::Path;
+#[cfg(feature = "std")]
 use std::path::PathBuf;
-use std::rc::Rc;
-use std::sync::Arc;
-use std::time::Duration;
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+#[cfg(feature = "std")]
+use std::sync::MutexGuard;
+#[cfg(feature = "std")]
+use std::sync::PoisonError;
+#[cfg(feature = "std")]
+use std::sync::RwLock;
+#[cfg(feature = "std")]
+use std::sync::RwLockReadGuard;
+#[cfg(feature = "std")]
+use std::time::SystemTime;
+#[cfg(feature = "std")]
+use std::time::UNIX_EPOCH;
+
+#[cfg(feature = "indexmap")]
+use indexmap::IndexMap;
+
+use format::Format;
+
+#[cfg(feature = "std")]
+pub mod channel;
+#[cfg(feature = "conformance")]
+pub mod conformance;
+#[cfg(feature = "conformance")]
+pub mod fuzz;
+pub mod format;
+pub mod via_serde;
+
+// Re-exports for the code generated by serde_roundtrip_derive, which cannot name
+// `std` or `core` directly, since the deriving crate may be `no_std`.
+#[doc(hidden)]
+pub mod export {
+    pub use core::default::Default;
+    pub use core::option::Option;
+    pub use core::result::Result;
+
+    use serde::Serialize;
+    use super::format::Format;
+
+    // Whether the format serializes the data as null, so that a #[roundtrip(some)] field
+    // round trips to None rather than Some.
+    pub fn serializes_as_null<S: ?Sized + Serialize, F: Format>(data: &S, format: F) -> bool {
+        super::format::serializes_as_null(data, format)
+    }
+
+    // The fields of a struct with #[roundtrip(versioned)], which a newer version of it
+    // with #[roundtrip(from = "...")] uses to round trip from it.
+    #[diagnostic::on_unimplemented(
+        message = "`{Self}` does not have a #[roundtrip(versioned)] attribute",
+        note = "a struct with #[roundtrip(from = \"...\")] round trips from a previous version \
+                which derives RoundTrip with #[roundtrip(versioned)]",
+    )]
+    pub trait Versioned {
+        // The names of the fields, with their serialized names
+        const FIELDS: &'static [(&'static str, &'static str)];
+        // The serialized names of the fields which may be serialized
+        const NAMES: &'static [&'static str];
+        // Whether the field with the given name is serialized
+        fn serializes(&self, field: &str) -> bool;
+    }
+
+    // The serialized name of the field with the given name.
+    pub const fn field_name(fields: &'static [(&'static str, &'static str)], field: &str) -> &'static str {
+        let mut index = 0;
+        while index < fields.len() {
+            if same_name(fields[index].0.as_bytes(), field.as_bytes()) {
+                return fields[index].1;
+            }
+            index += 1;
+        }
+        panic!("the field given by #[roundtrip(from = \"...\")] is not a field of the previous version")
+    }
+
+    // Whether a field serialized with the given name is deserialized by a field with the given
+    // name and aliases, which #[roundtrip(from = "...")] checks at compile time.
+    pub const fn accepts_name(name: &str, names: &[&str]) -> bool {
+        let mut index = 0;
+        while index < names.len() {
+            if same_name(name.as_bytes(), names[index].as_bytes()) {
+                return true;
+            }
+            index += 1;
+        }
+        false
+    }
+
+    // Whether every field serialized with one of the given names is deserialized by a field
+    // with one of the other names, which #[roundtrip(from = "...")] checks for a type
+    // with #[serde(deny_unknown_fields)].
+    pub const fn accepts_names(names: &[&str], other: &[&str]) -> bool {
+        let mut index = 0;
+        while index < names.len() {
+            if !accepts_name(names[index], other) {
+                return false;
+            }
+            index += 1;
+        }
+        true
+    }
+
+    const fn same_name(name: &[u8], other: &[u8]) -> bool {
+        if name.len() != other.len() {
+            return false;
+        }
+        let mut index = 0;
+        while index < name.len() {
+            if name[index] != other[index] {
+                return false;
+            }
+            index += 1;
+        }
+        true
+    }
+}
 
 /// This trait specifies when it's OK to perform a serialize-then-deserialiize round trip
 ///
-/// If `S: RoundTrip<T>` then the serialization format of `S` is compatible
-/// with the deserialization format of `T`.
-pub trait RoundTrip<Target: Deserialize>: Serialize {
+/// If `S: RoundTrip<'de, T>` then the serialization format of `S` is compatible
+/// with the deserialization format of `T`. As in serde's `Deserialize<'de>`,
+/// the lifetime `'de` is the lifetime of the data being deserialized, which for a round trip
+/// is the source, so for example a `String` can be round tripped as a `&'de str`.
+pub trait RoundTrip<'de, Target: Deserialize<'de>>: Serialize {
     /// This function specifies the behaviour of a round-trip.
-    /// If `S: RoundTrip<T>` then serializing `data:S` and then deserializing
+    /// If `S: RoundTrip<'de, T>` then serializing `data:S` and then deserializing
     /// it at type `T` should produce the same result as `Ok(data.round_trip())`.
-    fn round_trip(&self) -> Target;
+    fn round_trip(&'de self) -> Target;
+    /// This function specifies the behaviour of a round-trip into an existing target.
+    /// It should have the same effect as `*target = data.round_trip()`,
+    /// but may reuse storage owned by the target.
+    fn round_trip_into(&'de self, target: &mut Target) {
+        *target = self.round_trip();
+    }
 }
 
+/// This trait specifies a round trip whose target does not borrow from its source.
+///
+/// `S: RoundTripOwned<T>` is the same as `S: for<'de> RoundTrip<'de, T>`,
+/// in the same way that serde's `DeserializeOwned` is the same as `for<'de> Deserialize<'de>`.
+pub trait RoundTripOwned<Target: DeserializeOwned>: for<'de> RoundTrip<'de, Target> {}
+
+impl<S:?Sized,T> RoundTripOwned<T> for S where
+    S: for<'de> RoundTrip<'de, T>,
+    T: DeserializeOwned,
+{}
+
 /// This is a helper trait used by `RoundTrip` implementations, which specifies
 /// that two deserializations are compatible.
 /// 
-/// If `T: SameDeserialization` then the deserialization format of `T` is compatible
+/// If `T: SameDeserialization<'de>` then the deserialization format of `T` is compatible
 /// with the deserialization format of `T::SameAs`.
-pub trait SameDeserialization: Deserialize {
+pub trait SameDeserialization<'de>: Deserialize<'de> {
     /// The type that has the same deserialization.
-    type SameAs: Deserialize;
+    type SameAs: Deserialize<'de>;
     /// This function specifies the behaviour of deserialization.
-    /// If `T: SameDeserialization` then deserializing at type `T` should
+    /// If `T: SameDeserialization<'de>` then deserializing at type `T` should
     /// produce the same result as deserializing at type `T::SameAs`
     /// then calling `T::from`.
     fn from(data: Self::SameAs) -> Self;
+    /// This function gives access to the data of a target, so that it can be updated in place.
+    /// If `T: SameDeserialization<'de>` then updating `data.as_same_mut()` with `x`
+    /// should have the same effect as `*data = T::from(x)`.
+    /// It returns `None` if the data cannot be updated in place, for example because it is shared.
+    fn as_same_mut(&mut self) -> Option<&mut Self::SameAs> {
+        None
+    }
+}
+
+/// This trait specifies a serialize-then-deserialize round trip which may fail.
+///
+/// If `S: TryRoundTrip<'de, T>` then serializing `data:S` and then deserializing
+/// it at type `T` should produce the same result as `data.try_round_trip()`,
+/// including reporting an error when the real round trip would.
+pub trait TryRoundTrip<'de, Target: Deserialize<'de>>: Serialize {
+    /// This function specifies the behaviour of a fallible round-trip.
+    fn try_round_trip(&'de self) -> Result<Target, RoundTripError>;
+}
+
+/// This trait specifies a serialize-then-deserialize round trip in a particular format.
+///
+/// If `S: RoundTripIn<'de, F, T>` then serializing `data:S` in a format described by
+/// the profile `F` and then deserializing it at type `T` should produce the same result
+/// as `data.round_trip_in(format)`, including the format's lossy behaviour,
+/// for example JSON serializing `NaN` as `null`. Every type which implements `TryRoundTrip<'de, T>`
+/// also implements `RoundTripIn<'de, F, T>` for any profile, except for derived types which
+/// need a self-describing format, such as internally tagged enums, and some types have extra
+/// round trips which are only sound in some formats.
+pub trait RoundTripIn<'de, F: Format, Target: Deserialize<'de>>: Serialize {
+    /// This function specifies the behaviour of a round-trip in a format.
+    fn round_trip_in(&'de self, format: F) -> Result<Target, RoundTripError>;
+}
+
+/// This trait specifies a serialize-then-deserialize round trip which consumes its source.
+///
+/// If `S: IntoRoundTrip<'de, T>` then `data.into_round_trip()` should produce the same
+/// result as `data.round_trip()`, but it may reuse allocations owned by `data`
+/// rather than cloning them.
+pub trait IntoRoundTrip<'de, Target: Deserialize<'de>>: RoundTrip<'de, Target> + Sized {
+    /// This function specifies the behaviour of a consuming round-trip.
+    fn into_round_trip(self) -> Target;
+}
+
+/// The error reported by a round trip which fails.
+///
+/// This plays the role of the serializer's or deserializer's error,
+/// and carries the same message that serde would report.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoundTripError {
+    message: String,
+}
+
+impl RoundTripError {
+    /// Create an error with the given message.
+    pub fn new<M: Display>(message: M) -> RoundTripError {
+        RoundTripError { message: message.to_string() }
+    }
+
+    /// The error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for RoundTripError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(&self.message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for RoundTripError {}
+
+// Without std, serde uses its own error trait, which has no required methods.
+#[cfg(not(feature = "std"))]
+impl serde::de::StdError for RoundTripError {}
+
+impl serde::ser::Error for RoundTripError {
+    fn custom<M: Display>(message: M) -> RoundTripError {
+        RoundTripError::new(message)
+    }
+}
+
+impl serde::de::Error for RoundTripError {
+    fn custom<M: Display>(message: M) -> RoundTripError {
+        RoundTripError::new(message)
+    }
+}
+
+/// Perform a round trip, checking it against really serializing then deserializing.
+///
+/// This returns `data.round_trip()`, after checking that it is equal to the result
+/// of serializing then deserializing `data` using the in-memory format of the `via_serde`
+/// module. It is intended for testing `RoundTrip` implementations, and panics
+/// with the path to the first difference if the results are not equal.
+/// The in-memory format owns its data, so the target cannot borrow from it.
+pub fn checked_round_trip<'de, S, T>(data: &'de S) -> T where
+    S: ?Sized + RoundTrip<'de, T>,
+    T: DeserializeOwned + Serialize + PartialEq + Debug,
+{
+    checked_round_trip_with(data, "serde", via_serde::round_trip)
+}
+
+/// Perform a round trip, checking it against the given serialization then deserialization.
+///
+/// This is the same as `checked_round_trip`, but the expected result is given by `serde`,
+/// for example serializing then deserializing in a real format, which is named by `description`
+/// in the panic message. The path to the first difference is found by serializing both results
+/// as content trees, so it does not depend on their `Debug` implementations.
+pub fn checked_round_trip_with<'de, S, T, F>(data: &'de S, description: &str, serde: F) -> T where
+    S: ?Sized + RoundTrip<'de, T>,
+    T: Deserialize<'de> + Serialize + PartialEq + Debug,
+    F: FnOnce(&'de S) -> Result<T, RoundTripError>,
+{
+    let result = data.round_trip();
+    let expected: T = match serde(data) {
+        Ok(expected) => expected,
+        Err(err) => panic!("round trip succeeded but {} failed: {}", description, err),
+    };
+    if result != expected {
+        differs(description, &result, &expected);
+    }
+    result
+}
+
+// Panic with the path to the first difference between the result of a round trip and the expected result.
+fn differs<T>(description: &str, result: &T, expected: &T) -> ! where
+    T: Serialize + Debug,
+{
+    let difference = via_serde::to_content(result)
+        .and_then(|result| Ok(result.difference(&via_serde::to_content(expected)?)));
+    match difference {
+        Ok(Some(difference)) => panic!("round trip differs from {}: {}", description, difference),
+        _ => panic!("round trip differs from {}: {:?} != {:?}", description, result, expected),
+    }
+}
+
+/// Assert that a round trip agrees with really serializing then deserializing.
+///
+/// `assert_round_trip!(value => Target)` checks `value.round_trip()` against the in-memory
+/// format of the `via_serde` module, and with the `conformance` feature,
+/// `assert_round_trip!(value => Target, format)` checks it against a `conformance::RealFormat`.
+/// If they disagree, it panics with the path to the first difference,
+/// for example `.contents[2].StructCase.0`.
+#[macro_export]
+macro_rules! assert_round_trip {
+    ($value:expr => $target:ty) => {{
+        let _: $target = $crate::checked_round_trip_with(&$value, "serde", $crate::via_serde::round_trip);
+    }};
+    ($value:expr => $target:ty, $format:expr) => {{
+        let format: $crate::conformance::RealFormat = $format;
+        let _: $target = $crate::checked_round_trip_with(&$value, format.name(), |data| format.round_trip(data));
+    }};
 }
 
 // Types which roundtrip using clone.
 
 macro_rules! roundtrip_via_clone {
     ($t:ty) => {
-        impl<T> RoundTrip<T> for $t
-            where T: SameDeserialization<SameAs=$t>
+        roundtrip_via_clone!($t, check_any);
+    };
+    ($t:ty, $check:path) => {
+        impl<'de,T> RoundTrip<'de, T> for $t
+            where T: SameDeserialization<'de, SameAs=$t>
+        {
+            fn round_trip(&'de self) -> T { T::from(self.clone()) }
+            fn round_trip_into(&'de self, target: &mut T) {
+                match target.as_same_mut() {
+                    Some(data) => data.clone_from(self),
+                    None => *target = self.round_trip(),
+                }
+            }
+        }
+        impl<'de,T> TryRoundTrip<'de, T> for $t
+            where T: SameDeserialization<'de, SameAs=$t>
+        {
+            fn try_round_trip(&'de self) -> Result<T, RoundTripError> { Ok(self.round_trip()) }
+        }
+        impl<'de,T,F> RoundTripIn<'de, F, T> for $t
+            where F: Format, T: SameDeserialization<'de, SameAs=$t>
+        {
+            fn round_trip_in(&'de self, format: F) -> Result<T, RoundTripError> {
+                $check(self, format)?;
+                self.try_round_trip()
+            }
+        }
+        impl<'de,T> IntoRoundTrip<'de, T> for $t
+            where T: SameDeserialization<'de, SameAs=$t>
         {
-            fn round_trip(&self) -> T { T::from(self.clone()) }
+            fn into_round_trip(self) -> T { T::from(self) }
         }
-        impl SameDeserialization for $t {
+        impl<'de> SameDeserialization<'de> for $t {
             type SameAs = $t;
             fn from(data: $t) -> $t { data }
+            fn as_same_mut(&mut self) -> Option<&mut $t> { Some(self) }
         }
     };
 }
 
+fn check_any<S,F>(_: &S, _: F) -> Result<(), RoundTripError> {
+    Ok(())
+}
+
+// Floats, which fail to round trip in formats which serialize NaN as null
+
+fn check_float<S,F>(data: &S, format: F) -> Result<(), RoundTripError> where
+    S: Copy + Into<f64>,
+    F: Format,
+{
+    if format.non_finite_floats_are_null() && !(*data).into().is_finite() {
+        Err(RoundTripError::new(format_args!("invalid type: null, expected {}", type_name::<S>())))
+    } else {
+        Ok(())
+    }
+}
+
 roundtrip_via_clone!(());
 roundtrip_via_clone!(ByteBuf);
+#[cfg(feature = "std")]
 roundtrip_via_clone!(CString);
 roundtrip_via_clone!(Duration);
+#[cfg(feature = "std")]
 roundtrip_via_clone!(IpAddr);
+#[cfg(feature = "std")]
 roundtrip_via_clone!(Ipv4Addr);
+#[cfg(feature = "std")]
 roundtrip_via_clone!(Ipv6Addr);
-roundtrip_via_clone!(PathBuf);
+#[cfg(feature = "std")]
 roundtrip_via_clone!(SocketAddr);
+#[cfg(feature = "std")]
 roundtrip_via_clone!(SocketAddrV4);
+#[cfg(feature = "std")]
 roundtrip_via_clone!(SocketAddrV6);
+roundtrip_via_clone!(NonZeroI128);
+roundtrip_via_clone!(NonZeroI16);
+roundtrip_via_clone!(NonZeroI32);
+roundtrip_via_clone!(NonZeroI64);
+roundtrip_via_clone!(NonZeroI8);
+roundtrip_via_clone!(NonZeroIsize);
+roundtrip_via_clone!(NonZeroU128);
+roundtrip_via_clone!(NonZeroU16);
+roundtrip_via_clone!(NonZeroU32);
+roundtrip_via_clone!(NonZeroU64);
+roundtrip_via_clone!(NonZeroU8);
+roundtrip_via_clone!(NonZeroUsize);
+#[cfg(all(feature = "std", any(unix, windows)))]
+roundtrip_via_clone!(OsString);
+roundtrip_via_clone!(Saturating<i128>);
+roundtrip_via_clone!(Saturating<i16>);
+roundtrip_via_clone!(Saturating<i32>);
+roundtrip_via_clone!(Saturating<i64>);
+roundtrip_via_clone!(Saturating<i8>);
+roundtrip_via_clone!(Saturating<isize>);
+roundtrip_via_clone!(Saturating<u128>);
+roundtrip_via_clone!(Saturating<u16>);
+roundtrip_via_clone!(Saturating<u32>);
+roundtrip_via_clone!(Saturating<u64>);
+roundtrip_via_clone!(Saturating<u8>);
+roundtrip_via_clone!(Saturating<usize>);
 roundtrip_via_clone!(String);
 roundtrip_via_clone!(bool);
 roundtrip_via_clone!(char);
-roundtrip_via_clone!(f32);
-roundtrip_via_clone!(f64);
+roundtrip_via_clone!(f32, check_float);
+roundtrip_via_clone!(f64, check_float);
+roundtrip_via_clone!(i128);
 roundtrip_via_clone!(i16);
 roundtrip_via_clone!(i32);
 roundtrip_via_clone!(i64);
 roundtrip_via_clone!(i8);
 roundtrip_via_clone!(isize);
+roundtrip_via_clone!(u128);
 roundtrip_via_clone!(u16);
 roundtrip_via_clone!(u32);
 roundtrip_via_clone!(u64);
@@ -106,407 +567,1975 @@ roundtrip_via_clone!(usize);
 // Types which roundtrip using to_owned.
 
 macro_rules! roundtrip_via_to_owned {
-    ($t:ty) => {
-        impl<T> RoundTrip<T> for $t
-            where T: SameDeserialization<SameAs=<$t as ToOwned>::Owned>
+    ($t:ty => $owned:ty) => {
+        impl<'de,T> RoundTrip<'de, T> for $t
+            where T: SameDeserialization<'de, SameAs=$owned>
         {
-            fn round_trip(&self) -> T { T::from(self.to_owned()) }
+            fn round_trip(&'de self) -> T { T::from(self.to_owned()) }
+            fn round_trip_into(&'de self, target: &mut T) {
+                match target.as_same_mut() {
+                    Some(data) => self.clone_into(data),
+                    None => *target = self.round_trip(),
+                }
+            }
         }
-    };
-}
-
-roundtrip_via_to_owned!(CStr);
-roundtrip_via_to_owned!(Path);
-roundtrip_via_to_owned!(str);
-
-// Type constructors which roundtrip by dereferencing to their type argument
-
-macro_rules! roundtrip_via_deref {
-    ($F: ident) => {
-        impl<S,T> RoundTrip<T> for $F<S> where
-            S: RoundTrip<T>,
-            T: Deserialize,
+        impl<'de,T> TryRoundTrip<'de, T> for $t
+            where T: SameDeserialization<'de, SameAs=$owned>
         {
-            fn round_trip(&self) -> T { T::from(self.deref().round_trip()) }
+            fn try_round_trip(&'de self) -> Result<T, RoundTripError> { Ok(self.round_trip()) }
         }
-        impl<T> SameDeserialization for $F<T> where
-            T: SameDeserialization,
+        impl<'de,T,F> RoundTripIn<'de, F, T> for $t
+            where F: Format, T: SameDeserialization<'de, SameAs=$owned>
         {
-            type SameAs = T::SameAs;
-            fn from(data: T::SameAs) -> $F<T> { $F::new(T::from(data)) }
+            fn round_trip_in(&'de self, _: F) -> Result<T, RoundTripError> { self.try_round_trip() }
         }
-    }
+    };
 }
 
-roundtrip_via_deref!(Arc);
-roundtrip_via_deref!(Box);
-roundtrip_via_deref!(Rc);
+roundtrip_via_to_owned!(Bytes => ByteBuf);
+#[cfg(feature = "std")]
+roundtrip_via_to_owned!(CStr => CString);
+roundtrip_via_to_owned!(str => String);
 
-// Fixed-size arrays
-
-macro_rules! array_impls {
-    ($zero:expr) => {
-        impl<S,T,Ts> RoundTrip<Ts> for [S; $zero] where
-            S: RoundTrip<T>,
-            T: Deserialize,
-            Ts: SameDeserialization<SameAs=[T; $zero]>,
-        {
-            fn round_trip(&self) -> Ts { Ts::from([]) }
-        }
-    };
+// Paths, which fail to serialize if they are not UTF-8
 
-    ($len:expr, $($indices:expr),*) => {
-        impl<S,T,Ts> RoundTrip<Ts> for [S; $len] where
-            S: RoundTrip<T>,
-            T: Deserialize,
-            Ts: SameDeserialization<SameAs=[T; $len]>,
-        {
-            fn round_trip(&self) -> Ts { Ts::from([ $(self[$len-($indices+1)].round_trip()),* ]) }
-        }
-        array_impls!($($indices),*);
-    };
+#[cfg(feature = "std")]
+fn check_path(path: &Path) -> Result<(), RoundTripError> {
+    match path.to_str() {
+        Some(_) => Ok(()),
+        None => Err(RoundTripError::new("path contains invalid UTF-8 characters")),
+    }
 }
 
-array_impls!(32, 31, 30, 29, 28, 27, 26, 25, 24, 23, 22, 21, 20, 19, 18, 17, 16, 15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0);
-
-// Arrays
-
-impl<S,T,Ts> RoundTrip<Ts> for Vec<S> where
-    S: RoundTrip<T>,
-    T: Deserialize,
-    Ts: SameDeserialization<SameAs=Vec<T>>
+#[cfg(feature = "std")]
+impl<'de,T> RoundTrip<'de, T> for Path
+    where T: SameDeserialization<'de, SameAs=PathBuf>
 {
-    fn round_trip(&self) -> Ts {
-        Ts::from(self.iter().map(RoundTrip::round_trip).collect())
+    fn round_trip(&'de self) -> T { T::from(self.to_owned()) }
+    fn round_trip_into(&'de self, target: &mut T) {
+        match target.as_same_mut() {
+            Some(data) => self.clone_into(data),
+            None => *target = self.round_trip(),
+        }
     }
 }
 
-impl<S,T,Ts> RoundTrip<Ts> for [S] where
-    S: RoundTrip<T>,
-    T: Deserialize,
-    Ts: SameDeserialization<SameAs=Vec<T>>
+#[cfg(feature = "std")]
+impl<'de,T> TryRoundTrip<'de, T> for Path
+    where T: SameDeserialization<'de, SameAs=PathBuf>
 {
-    fn round_trip(&self) -> Ts {
-        Ts::from(self.iter().map(RoundTrip::round_trip).collect())
+    fn try_round_trip(&'de self) -> Result<T, RoundTripError> {
+        check_path(self).map(|()| self.round_trip())
     }
 }
 
-impl<'a,T> RoundTrip<T> for Bytes<'a>
-    where T: SameDeserialization<SameAs=ByteBuf>
+#[cfg(feature = "std")]
+impl<'de,T,F> RoundTripIn<'de, F, T> for Path
+    where F: Format, T: SameDeserialization<'de, SameAs=PathBuf>
 {
-    fn round_trip(&self) -> T { T::from(ByteBuf::from(self.to_vec())) }
+    fn round_trip_in(&'de self, _: F) -> Result<T, RoundTripError> { self.try_round_trip() }
 }
 
-impl<T> SameDeserialization for Vec<T> where
-    T: Deserialize,
+#[cfg(feature = "std")]
+impl<'de,T> RoundTrip<'de, T> for PathBuf
+    where T: SameDeserialization<'de, SameAs=PathBuf>
 {
-    type SameAs = Vec<T>;
-    fn from(data: Vec<T>) -> Vec<T> { data }
+    fn round_trip(&'de self) -> T { T::from(self.clone()) }
+    fn round_trip_into(&'de self, target: &mut T) {
+        match target.as_same_mut() {
+            Some(data) => data.clone_from(self),
+            None => *target = self.round_trip(),
+        }
+    }
 }
 
-// Refs
-
-impl<'a,S:?Sized,T> RoundTrip<T> for &'a S where
-    S: RoundTrip<T>,
-    T: Deserialize,
+#[cfg(feature = "std")]
+impl<'de,T> TryRoundTrip<'de, T> for PathBuf
+    where T: SameDeserialization<'de, SameAs=PathBuf>
 {
-    fn round_trip(&self) -> T { (**self).round_trip() }
+    fn try_round_trip(&'de self) -> Result<T, RoundTripError> {
+        check_path(self).map(|()| self.round_trip())
+    }
 }
 
-impl<'a,S:?Sized,T> RoundTrip<T> for &'a mut S where
-    S: RoundTrip<T>,
-    T: Deserialize,
+#[cfg(feature = "std")]
+impl<'de,T,F> RoundTripIn<'de, F, T> for PathBuf
+    where F: Format, T: SameDeserialization<'de, SameAs=PathBuf>
 {
-    fn round_trip(&self) -> T { (**self).round_trip() }
+    fn round_trip_in(&'de self, _: F) -> Result<T, RoundTripError> { self.try_round_trip() }
 }
 
-impl<'a,S:?Sized,T> RoundTrip<T> for Cow<'a,S> where
-    S: ToOwned + RoundTrip<T>,
-    T: Deserialize,
+#[cfg(feature = "std")]
+impl<'de,T> IntoRoundTrip<'de, T> for PathBuf
+    where T: SameDeserialization<'de, SameAs=PathBuf>
 {
-    fn round_trip(&self) -> T { (**self).round_trip() }
+    fn into_round_trip(self) -> T { T::from(self) }
 }
 
-impl<'a,T:?Sized> SameDeserialization for Cow<'a,T> where
-    T: ToOwned,
-    T::Owned: SameDeserialization,
-{
-    type SameAs = <T::Owned as SameDeserialization>::SameAs;
-    fn from(data: Self::SameAs) -> Self { Cow::Owned(SameDeserialization::from(data)) }
+#[cfg(feature = "std")]
+impl<'de> SameDeserialization<'de> for PathBuf {
+    type SameAs = PathBuf;
+    fn from(data: PathBuf) -> PathBuf { data }
+    fn as_same_mut(&mut self) -> Option<&mut PathBuf> { Some(self) }
 }
 
-// Tuples
+// Types which roundtrip as a reference into the source, for example a `String` as a `&str`.
+// As in serde, a target can borrow from the data being deserialized for as long as `'de`.
 
-impl <S0, T0, T> RoundTrip<T> for (S0,) where
-    S0: RoundTrip<T0>,
-    T0: Deserialize,
-    T: SameDeserialization<SameAs=(T0,)>,
-{
-    fn round_trip(&self) -> T { T::from((self.0.round_trip(),)) }
+macro_rules! roundtrip_via_borrow {
+    ($s:ty => $t:ty) => {
+        roundtrip_via_borrow!($s => $t, check_borrow);
+    };
+    ($s:ty => $t:ty, $check:path) => {
+        impl<'de: 'b,'b> RoundTrip<'de, &'b $t> for $s {
+            fn round_trip(&'de self) -> &'b $t { self.borrow() }
+        }
+        impl<'de: 'b,'b> TryRoundTrip<'de, &'b $t> for $s {
+            fn try_round_trip(&'de self) -> Result<&'b $t, RoundTripError> {
+                let result = self.round_trip();
+                $check(result).map(|()| result)
+            }
+        }
+        impl<'de: 'b,'b,F> RoundTripIn<'de, F, &'b $t> for $s
+            where F: Format
+        {
+            fn round_trip_in(&'de self, _: F) -> Result<&'b $t, RoundTripError> { self.try_round_trip() }
+        }
+    };
 }
 
-impl <T> SameDeserialization for (T,) where
-    T: Deserialize,
-{
-    type SameAs = (T,);
-    fn from(data: (T,)) -> (T,) { data }
+fn check_borrow<T: ?Sized>(_: &T) -> Result<(), RoundTripError> {
+    Ok(())
 }
 
-macro_rules! tuple_impls {
-    ($($xs:ident : $Ss:ident => $Ts:ident),*) => {
-        impl<$($Ss),*,$($Ts),*,T> RoundTrip<T> for ($($Ss),*) where
-            $($Ss: RoundTrip<$Ts>),*,
-            $($Ts: Deserialize),*,
-            T: SameDeserialization<SameAs=($($Ts),*)>,
-        {
-            fn round_trip(&self) -> T {
-                let ($(ref $xs),*) = *self;
-                T::from(($($xs.round_trip()),*))
+roundtrip_via_borrow!(ByteBuf => Bytes);
+roundtrip_via_borrow!(Bytes => Bytes);
+#[cfg(feature = "std")]
+roundtrip_via_borrow!(Path => Path, check_path);
+#[cfg(feature = "std")]
+roundtrip_via_borrow!(PathBuf => Path, check_path);
+roundtrip_via_borrow!(String => str);
+roundtrip_via_borrow!(str => str);
+
+// Types which roundtrip as a different type with a compatible serialization,
+// for example a `String` as a `PathBuf`, or a `PathBuf` as a `String` if it is UTF-8.
+
+macro_rules! roundtrip_via_convert {
+    ($s:ty => $t:ty, $convert:expr) => {
+        roundtrip_via_convert!($s => $t, $convert, check_borrow);
+    };
+    ($s:ty => $t:ty, $convert:expr, $check:path) => {
+        impl<'de> RoundTrip<'de, $t> for $s {
+            fn round_trip(&'de self) -> $t { $convert(self) }
+        }
+        impl<'de> TryRoundTrip<'de, $t> for $s {
+            fn try_round_trip(&'de self) -> Result<$t, RoundTripError> {
+                $check(self).map(|()| self.round_trip())
             }
         }
-        impl<$($Ts),*> SameDeserialization for ($($Ts),*) where
-            $($Ts: Deserialize),*,
+        impl<'de,F> RoundTripIn<'de, F, $t> for $s
+            where F: Format
         {
-            type SameAs = ($($Ts),*);
-            fn from(data: ($($Ts),*)) -> ($($Ts),*) { data }
+            fn round_trip_in(&'de self, _: F) -> Result<$t, RoundTripError> { self.try_round_trip() }
         }
     };
 }
 
-tuple_impls!(x_0: S0 => T0, x_1: S1 => T1);
-
-tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2);
-
-tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3);
-
-tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
-             x_4: S4 => T4);
-
-tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
-             x_4: S4 => T4, x_5: S5 => T5);
-
-tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
-             x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6);
-
-tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
-             x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7);
+#[cfg(feature = "std")]
+fn path_to_string(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
 
-tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
-             x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
-             x_8: S8 => T8);
+fn bytes_to_byte_buf(data: &[u8]) -> ByteBuf {
+    ByteBuf::from(data)
+}
 
-tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
-             x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
-             x_8: S8 => T8, x_9: S9 => T9);
+#[cfg(feature = "std")]
+roundtrip_via_convert!(Path => String, path_to_string, check_path);
+#[cfg(feature = "std")]
+roundtrip_via_convert!(PathBuf => String, path_to_string, check_path);
+#[cfg(feature = "std")]
+roundtrip_via_convert!(String => PathBuf, From::from);
+#[cfg(feature = "std")]
+roundtrip_via_convert!(str => PathBuf, From::from);
+roundtrip_via_convert!(Vec<u8> => ByteBuf, bytes_to_byte_buf);
+roundtrip_via_convert!([u8] => ByteBuf, bytes_to_byte_buf);
+
+#[cfg(feature = "std")]
+impl<'de> IntoRoundTrip<'de, PathBuf> for String {
+    fn into_round_trip(self) -> PathBuf { self.into() }
+}
 
-tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
-             x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
-             x_8: S8 => T8, x_9: S9 => T9, x_a: SA => TA);
+impl<'de> IntoRoundTrip<'de, ByteBuf> for Vec<u8> {
+    fn into_round_trip(self) -> ByteBuf { ByteBuf::from(self) }
+}
 
-tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
-             x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
-             x_8: S8 => T8, x_9: S9 => T9, x_a: SA => TA, x_b: SB => TB);
+// Non-zero integers, which round trip to their integer, and from it if it is not zero.
 
-tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
-             x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
-             x_8: S8 => T8, x_9: S9 => T9, x_a: SA => TA, x_b: SB => TB,
-             x_c: SC => TC);
+macro_rules! roundtrip_via_nonzero {
+    ($nonzero:ident => $t:ident) => {
+        impl<'de> RoundTrip<'de, $t> for $nonzero {
+            fn round_trip(&'de self) -> $t { self.get() }
+        }
+        impl<'de> TryRoundTrip<'de, $t> for $nonzero {
+            fn try_round_trip(&'de self) -> Result<$t, RoundTripError> { Ok(self.get()) }
+        }
+        impl<'de,F> RoundTripIn<'de, F, $t> for $nonzero
+            where F: Format
+        {
+            fn round_trip_in(&'de self, _: F) -> Result<$t, RoundTripError> { Ok(self.get()) }
+        }
+        impl<'de> IntoRoundTrip<'de, $t> for $nonzero {
+            fn into_round_trip(self) -> $t { self.get() }
+        }
+        impl<'de> TryRoundTrip<'de, $nonzero> for $t {
+            fn try_round_trip(&'de self) -> Result<$nonzero, RoundTripError> {
+                $nonzero::new(*self).ok_or_else(|| RoundTripError::new(concat!("invalid value: integer `0`, expected a nonzero ", stringify!($t))))
+            }
+        }
+        impl<'de,F> RoundTripIn<'de, F, $nonzero> for $t
+            where F: Format
+        {
+            fn round_trip_in(&'de self, _: F) -> Result<$nonzero, RoundTripError> { self.try_round_trip() }
+        }
+    };
+}
 
-tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
-             x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
-             x_8: S8 => T8, x_9: S9 => T9, x_a: SA => TA, x_b: SB => TB,
-             x_c: SC => TC, x_d: SD => TD);
+roundtrip_via_nonzero!(NonZeroI128 => i128);
+roundtrip_via_nonzero!(NonZeroI16 => i16);
+roundtrip_via_nonzero!(NonZeroI32 => i32);
+roundtrip_via_nonzero!(NonZeroI64 => i64);
+roundtrip_via_nonzero!(NonZeroI8 => i8);
+roundtrip_via_nonzero!(NonZeroIsize => isize);
+roundtrip_via_nonzero!(NonZeroU128 => u128);
+roundtrip_via_nonzero!(NonZeroU16 => u16);
+roundtrip_via_nonzero!(NonZeroU32 => u32);
+roundtrip_via_nonzero!(NonZeroU64 => u64);
+roundtrip_via_nonzero!(NonZeroU8 => u8);
+roundtrip_via_nonzero!(NonZeroUsize => usize);
+
+// System times, which fail to serialize if they are before the Unix epoch
+
+#[cfg(feature = "std")]
+fn check_system_time(time: &SystemTime) -> Result<(), RoundTripError> {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(RoundTripError::new("SystemTime must be later than UNIX_EPOCH")),
+    }
+}
 
-tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
-             x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
-             x_8: S8 => T8, x_9: S9 => T9, x_a: SA => TA, x_b: SB => TB,
-             x_c: SC => TC, x_d: SD => TD, x_e: SE => TE);
+#[cfg(feature = "std")]
+impl<'de,T> RoundTrip<'de, T> for SystemTime
+    where T: SameDeserialization<'de, SameAs=SystemTime>
+{
+    fn round_trip(&'de self) -> T { T::from(*self) }
+}
 
-tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
-             x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
-             x_8: S8 => T8, x_9: S9 => T9, x_a: SA => TA, x_b: SB => TB,
-             x_c: SC => TC, x_d: SD => TD, x_e: SE => TE, x_f: SF => TF);
+#[cfg(feature = "std")]
+impl<'de,T> TryRoundTrip<'de, T> for SystemTime
+    where T: SameDeserialization<'de, SameAs=SystemTime>
+{
+    fn try_round_trip(&'de self) -> Result<T, RoundTripError> {
+        check_system_time(self).map(|()| self.round_trip())
+    }
+}
 
-// Phantom data
+#[cfg(feature = "std")]
+impl<'de,T,F> RoundTripIn<'de, F, T> for SystemTime
+    where F: Format, T: SameDeserialization<'de, SameAs=SystemTime>
+{
+    fn round_trip_in(&'de self, _: F) -> Result<T, RoundTripError> { self.try_round_trip() }
+}
 
-impl<S,T> RoundTrip<T> for PhantomData<S> where
-    T: SameDeserialization<SameAs=PhantomData<S>>,
+#[cfg(feature = "std")]
+impl<'de,T> IntoRoundTrip<'de, T> for SystemTime
+    where T: SameDeserialization<'de, SameAs=SystemTime>
 {
-    fn round_trip(&self) -> T { T::from(PhantomData) }
+    fn into_round_trip(self) -> T { T::from(self) }
 }
 
-impl<T> SameDeserialization for PhantomData<T> {
-    type SameAs = PhantomData<T>;
-    fn from(data: PhantomData<T>) -> PhantomData<T> { data }
+#[cfg(feature = "std")]
+impl<'de> SameDeserialization<'de> for SystemTime {
+    type SameAs = SystemTime;
+    fn from(data: SystemTime) -> SystemTime { data }
+    fn as_same_mut(&mut self) -> Option<&mut SystemTime> { Some(self) }
 }
 
-// Options
+// Type constructors which roundtrip by dereferencing to their type argument.
+// An unsized owner, such as a `Box<str>`, deserializes via its owned counterpart, such as a `String`.
 
-impl<S0,T0,T> RoundTrip<T> for Option<S0> where
-    S0: RoundTrip<T0>,
-    T0: Deserialize,
-    T: SameDeserialization<SameAs=Option<T0>>,
-{
-    fn round_trip(&self) -> T { T::from(self.as_ref().map(RoundTrip::round_trip)) }
+macro_rules! roundtrip_via_deref {
+    ($F: ident, $get_mut: path) => {
+        impl<'de,S:?Sized,T> RoundTrip<'de, T> for $F<S> where
+            S: RoundTrip<'de, T>,
+            T: Deserialize<'de>,
+        {
+            fn round_trip(&'de self) -> T { T::from(self.deref().round_trip()) }
+            fn round_trip_into(&'de self, target: &mut T) { self.deref().round_trip_into(target) }
+        }
+        impl<'de,S:?Sized,T> TryRoundTrip<'de, T> for $F<S> where
+            S: TryRoundTrip<'de, T>,
+            T: Deserialize<'de>,
+        {
+            fn try_round_trip(&'de self) -> Result<T, RoundTripError> { self.deref().try_round_trip() }
+        }
+        impl<'de,S:?Sized,T,F> RoundTripIn<'de, F, T> for $F<S> where
+            F: Format,
+            S: RoundTripIn<'de, F, T>,
+            T: Deserialize<'de>,
+        {
+            fn round_trip_in(&'de self, format: F) -> Result<T, RoundTripError> { self.deref().round_trip_in(format) }
+        }
+        impl<'de,T> SameDeserialization<'de> for $F<T> where
+            T: SameDeserialization<'de>,
+        {
+            type SameAs = T::SameAs;
+            fn from(data: T::SameAs) -> $F<T> { $F::new(T::from(data)) }
+            fn as_same_mut(&mut self) -> Option<&mut T::SameAs> { $get_mut(self).and_then(T::as_same_mut) }
+        }
+        impl<'de> SameDeserialization<'de> for $F<str> {
+            type SameAs = String;
+            fn from(data: String) -> $F<str> { data.into() }
+        }
+        impl<'de,T> SameDeserialization<'de> for $F<[T]> where
+            T: Deserialize<'de>,
+        {
+            type SameAs = Vec<T>;
+            fn from(data: Vec<T>) -> $F<[T]> { data.into() }
+        }
+        #[cfg(feature = "std")]
+        impl<'de> SameDeserialization<'de> for $F<CStr> {
+            type SameAs = CString;
+            fn from(data: CString) -> $F<CStr> { data.into() }
+        }
+        #[cfg(feature = "std")]
+        impl<'de> SameDeserialization<'de> for $F<Path> {
+            type SameAs = PathBuf;
+            fn from(data: PathBuf) -> $F<Path> { data.into() }
+        }
+    }
 }
 
-impl<T> SameDeserialization for Option<T> where
-    T: Deserialize
-{
-    type SameAs = Option<T>;
-    fn from(data: Option<T>) -> Option<T> { data }
+fn box_get_mut<T>(data: &mut Box<T>) -> Option<&mut T> {
+    Some(data)
 }
 
-// Results
+roundtrip_via_deref!(Arc, Arc::get_mut);
+roundtrip_via_deref!(Box, box_get_mut);
+roundtrip_via_deref!(Rc, Rc::get_mut);
 
-impl<S0,S1,T0,T1,T> RoundTrip<T> for Result<S0,S1> where
-    S0: RoundTrip<T0>,
-    S1: RoundTrip<T1>,
-    T0: Deserialize,
-    T1: Deserialize,
-    T: SameDeserialization<SameAs=Result<T0,T1>>,
+impl<'de,S,T> IntoRoundTrip<'de, T> for Box<S> where
+    S: IntoRoundTrip<'de, T>,
+    T: Deserialize<'de>,
 {
-    fn round_trip(&self) -> T { T::from(self.as_ref().map(RoundTrip::round_trip).map_err(RoundTrip::round_trip)) }
+    fn into_round_trip(self) -> T { (*self).into_round_trip() }
 }
 
-impl<T0,T1> SameDeserialization for Result<T0,T1> where
-    T0: Deserialize,
-    T1: Deserialize,
+impl<'de,T> IntoRoundTrip<'de, T> for Box<str> where
+    T: SameDeserialization<'de, SameAs=String>,
 {
-    type SameAs = Result<T0,T1>;
-    fn from(data: Result<T0,T1>) -> Result<T0,T1> { data }
+    fn into_round_trip(self) -> T { T::from(self.into_string()) }
 }
 
-// BinaryHeap
-
-impl<S0,T0,T> RoundTrip<T> for BinaryHeap<S0> where
-    S0: Ord+RoundTrip<T0>,
-    T0: Ord+Deserialize,
-    T: SameDeserialization<SameAs=BinaryHeap<T0>>,
+impl<'de,S,T,Ts> IntoRoundTrip<'de, Ts> for Box<[S]> where
+    S: IntoRoundTrip<'de, T>,
+    T: Deserialize<'de>,
+    Ts: SameDeserialization<'de, SameAs=Vec<T>>,
 {
-    fn round_trip(&self) -> T { T::from(self.iter().map(RoundTrip::round_trip).collect()) }
+    fn into_round_trip(self) -> Ts { self.into_vec().into_round_trip() }
 }
 
-impl<T0> SameDeserialization for BinaryHeap<T0> where
-    T0: Ord+Deserialize,
-{
-    type SameAs = BinaryHeap<T0>;
-    fn from(data: BinaryHeap<T0>) -> BinaryHeap<T0> { data }
+// Shared pointers only give up their contents if there are no other references,
+// and otherwise the contents are round tripped by reference, so the target cannot borrow from them.
+
+macro_rules! into_roundtrip_via_try_unwrap {
+    ($F: ident) => {
+        impl<'de,S,T> IntoRoundTrip<'de, T> for $F<S> where
+            S: for<'x> IntoRoundTrip<'x, T>,
+            T: DeserializeOwned,
+        {
+            fn into_round_trip(self) -> T {
+                match $F::try_unwrap(self) {
+                    Ok(data) => data.into_round_trip(),
+                    Err(shared) => shared.round_trip(),
+                }
+            }
+        }
+    }
 }
 
-// BTreeMap
+into_roundtrip_via_try_unwrap!(Arc);
+into_roundtrip_via_try_unwrap!(Rc);
+
+// Fixed-size arrays, which serde can only serialize up to length 32.
 
-impl<S0,S1,T0,T1,T> RoundTrip<T> for BTreeMap<S0,S1> where
-    S0: Ord+RoundTrip<T0>,
-    S1: RoundTrip<T1>,
-    T0: Ord+Deserialize,
-    T1: Deserialize,
-    T: SameDeserialization<SameAs=BTreeMap<T0,T1>>,
+// Build an array from a fallible function of each index, stopping at the first error.
+fn try_array_from_fn<T,E,G,const N: usize>(mut f: G) -> Result<[T; N], E> where
+    G: FnMut(usize) -> Result<T, E>,
 {
-    fn round_trip(&self) -> T {
-        T::from(self.iter().map(|(x1,x2)| (x1.round_trip(), x2.round_trip())).collect())
+    let mut error = None;
+    let array: [Option<T>; N] = core::array::from_fn(|index| match error {
+        Some(_) => None,
+        None => f(index).map_err(|err| error = Some(err)).ok(),
+    });
+    match error {
+        Some(err) => Err(err),
+        None => Ok(array.map(|data| data.expect("Unexpected missing array element"))),
     }
 }
 
-impl<T0,T1> SameDeserialization for BTreeMap<T0,T1> where
-    T0: Ord+Deserialize,
-    T1: Deserialize,
+impl<'de,S,T,Ts,const N: usize> RoundTrip<'de, Ts> for [S; N] where
+    [S; N]: Serialize,
+    S: RoundTrip<'de, T>,
+    T: Deserialize<'de>,
+    Ts: SameDeserialization<'de, SameAs=[T; N]>,
 {
-    type SameAs = BTreeMap<T0,T1>;
+    fn round_trip(&'de self) -> Ts { Ts::from(core::array::from_fn(|index| self[index].round_trip())) }
+    fn round_trip_into(&'de self, target: &mut Ts) {
+        match target.as_same_mut() {
+            Some(data) => {
+                for (x, y) in self.iter().zip(data.iter_mut()) {
+                    x.round_trip_into(y);
+                }
+            },
+            None => *target = self.round_trip(),
+        }
+    }
+}
+
+impl<'de,S,T,Ts,const N: usize> TryRoundTrip<'de, Ts> for [S; N] where
+    [S; N]: Serialize,
+    S: TryRoundTrip<'de, T>,
+    T: Deserialize<'de>,
+    Ts: SameDeserialization<'de, SameAs=[T; N]>,
+{
+    fn try_round_trip(&'de self) -> Result<Ts, RoundTripError> {
+        try_array_from_fn(|index| self[index].try_round_trip()).map(Ts::from)
+    }
+}
+
+impl<'de,S,T,Ts,F,const N: usize> RoundTripIn<'de, F, Ts> for [S; N] where
+    [S; N]: Serialize,
+    F: Format,
+    S: RoundTripIn<'de, F, T>,
+    T: Deserialize<'de>,
+    Ts: SameDeserialization<'de, SameAs=[T; N]>,
+{
+    fn round_trip_in(&'de self, format: F) -> Result<Ts, RoundTripError> {
+        try_array_from_fn(|index| self[index].round_trip_in(format)).map(Ts::from)
+    }
+}
+
+impl<'de,S,T,Ts,const N: usize> IntoRoundTrip<'de, Ts> for [S; N] where
+    [S; N]: Serialize,
+    S: IntoRoundTrip<'de, T>,
+    T: Deserialize<'de>,
+    Ts: SameDeserialization<'de, SameAs=[T; N]>,
+{
+    fn into_round_trip(self) -> Ts { Ts::from(self.map(IntoRoundTrip::into_round_trip)) }
+}
+
+impl<'de,T,const N: usize> SameDeserialization<'de> for [T; N] where
+    [T; N]: Deserialize<'de>,
+{
+    type SameAs = [T; N];
+    fn from(data: [T; N]) -> [T; N] { data }
+    fn as_same_mut(&mut self) -> Option<&mut [T; N]> { Some(self) }
+}
+
+// Arrays
+
+// Round trip into a vector, reusing its buffer and its elements' storage.
+fn round_trip_into_vec<'de,S,T>(source: &'de [S], target: &mut Vec<T>) where
+    S: RoundTrip<'de, T>,
+    T: Deserialize<'de>,
+{
+    target.truncate(source.len());
+    for (x, y) in source.iter().zip(target.iter_mut()) {
+        x.round_trip_into(y);
+    }
+    let len = target.len();
+    target.extend(source[len..].iter().map(RoundTrip::round_trip));
+}
+
+impl<'de,S,T,Ts> RoundTrip<'de, Ts> for Vec<S> where
+    S: RoundTrip<'de, T>,
+    T: Deserialize<'de>,
+    Ts: SameDeserialization<'de, SameAs=Vec<T>>
+{
+    fn round_trip(&'de self) -> Ts {
+        Ts::from(self.iter().map(RoundTrip::round_trip).collect())
+    }
+    fn round_trip_into(&'de self, target: &mut Ts) {
+        match target.as_same_mut() {
+            Some(data) => round_trip_into_vec(self, data),
+            None => *target = self.round_trip(),
+        }
+    }
+}
+
+impl<'de,S,T,Ts> TryRoundTrip<'de, Ts> for Vec<S> where
+    S: TryRoundTrip<'de, T>,
+    T: Deserialize<'de>,
+    Ts: SameDeserialization<'de, SameAs=Vec<T>>
+{
+    fn try_round_trip(&'de self) -> Result<Ts, RoundTripError> {
+        self.iter().map(TryRoundTrip::try_round_trip).collect::<Result<_,_>>().map(Ts::from)
+    }
+}
+
+impl<'de,S,T,Ts,F> RoundTripIn<'de, F, Ts> for Vec<S> where
+    F: Format,
+    S: RoundTripIn<'de, F, T>,
+    T: Deserialize<'de>,
+    Ts: SameDeserialization<'de, SameAs=Vec<T>>
+{
+    fn round_trip_in(&'de self, format: F) -> Result<Ts, RoundTripError> {
+        self.iter().map(|x| x.round_trip_in(format)).collect::<Result<_,_>>().map(Ts::from)
+    }
+}
+
+// When S and T have the same size and alignment, collecting reuses the vector's buffer.
+impl<'de,S,T,Ts> IntoRoundTrip<'de, Ts> for Vec<S> where
+    S: IntoRoundTrip<'de, T>,
+    T: Deserialize<'de>,
+    Ts: SameDeserialization<'de, SameAs=Vec<T>>
+{
+    fn into_round_trip(self) -> Ts {
+        Ts::from(self.into_iter().map(IntoRoundTrip::into_round_trip).collect())
+    }
+}
+
+impl<'de,S,T,Ts> RoundTrip<'de, Ts> for [S] where
+    S: RoundTrip<'de, T>,
+    T: Deserialize<'de>,
+    Ts: SameDeserialization<'de, SameAs=Vec<T>>
+{
+    fn round_trip(&'de self) -> Ts {
+        Ts::from(self.iter().map(RoundTrip::round_trip).collect())
+    }
+    fn round_trip_into(&'de self, target: &mut Ts) {
+        match target.as_same_mut() {
+            Some(data) => round_trip_into_vec(self, data),
+            None => *target = self.round_trip(),
+        }
+    }
+}
+
+impl<'de,S,T,Ts> TryRoundTrip<'de, Ts> for [S] where
+    S: TryRoundTrip<'de, T>,
+    T: Deserialize<'de>,
+    Ts: SameDeserialization<'de, SameAs=Vec<T>>
+{
+    fn try_round_trip(&'de self) -> Result<Ts, RoundTripError> {
+        self.iter().map(TryRoundTrip::try_round_trip).collect::<Result<_,_>>().map(Ts::from)
+    }
+}
+
+impl<'de,S,T,Ts,F> RoundTripIn<'de, F, Ts> for [S] where
+    F: Format,
+    S: RoundTripIn<'de, F, T>,
+    T: Deserialize<'de>,
+    Ts: SameDeserialization<'de, SameAs=Vec<T>>
+{
+    fn round_trip_in(&'de self, format: F) -> Result<Ts, RoundTripError> {
+        self.iter().map(|x| x.round_trip_in(format)).collect::<Result<_,_>>().map(Ts::from)
+    }
+}
+
+impl<'de,T> SameDeserialization<'de> for Vec<T> where
+    T: Deserialize<'de>,
+{
+    type SameAs = Vec<T>;
+    fn from(data: Vec<T>) -> Vec<T> { data }
+    fn as_same_mut(&mut self) -> Option<&mut Vec<T>> { Some(self) }
+}
+
+// Refs
+
+impl<'de,S:?Sized,T> RoundTrip<'de, T> for &S where
+    S: RoundTrip<'de, T>,
+    T: Deserialize<'de>,
+{
+    fn round_trip(&'de self) -> T { (**self).round_trip() }
+    fn round_trip_into(&'de self, target: &mut T) { (**self).round_trip_into(target) }
+}
+
+impl<'de,S:?Sized,T> TryRoundTrip<'de, T> for &S where
+    S: TryRoundTrip<'de, T>,
+    T: Deserialize<'de>,
+{
+    fn try_round_trip(&'de self) -> Result<T, RoundTripError> { (**self).try_round_trip() }
+}
+
+impl<'de,S:?Sized,T,F> RoundTripIn<'de, F, T> for &S where
+    F: Format,
+    S: RoundTripIn<'de, F, T>,
+    T: Deserialize<'de>,
+{
+    fn round_trip_in(&'de self, format: F) -> Result<T, RoundTripError> { (**self).round_trip_in(format) }
+}
+
+// A reference can only be consumed if the target borrows for no longer than the reference.
+impl<'a,S:?Sized,T> IntoRoundTrip<'a, T> for &'a S where
+    S: RoundTrip<'a, T>,
+    T: Deserialize<'a>,
+{
+    fn into_round_trip(self) -> T { self.round_trip() }
+}
+
+impl<'de,S:?Sized,T> RoundTrip<'de, T> for &mut S where
+    S: RoundTrip<'de, T>,
+    T: Deserialize<'de>,
+{
+    fn round_trip(&'de self) -> T { (**self).round_trip() }
+    fn round_trip_into(&'de self, target: &mut T) { (**self).round_trip_into(target) }
+}
+
+impl<'de,S:?Sized,T> TryRoundTrip<'de, T> for &mut S where
+    S: TryRoundTrip<'de, T>,
+    T: Deserialize<'de>,
+{
+    fn try_round_trip(&'de self) -> Result<T, RoundTripError> { (**self).try_round_trip() }
+}
+
+impl<'de,S:?Sized,T,F> RoundTripIn<'de, F, T> for &mut S where
+    F: Format,
+    S: RoundTripIn<'de, F, T>,
+    T: Deserialize<'de>,
+{
+    fn round_trip_in(&'de self, format: F) -> Result<T, RoundTripError> { (**self).round_trip_in(format) }
+}
+
+impl<'a,S:?Sized,T> IntoRoundTrip<'a, T> for &'a mut S where
+    S: RoundTrip<'a, T>,
+    T: Deserialize<'a>,
+{
+    fn into_round_trip(self) -> T {
+        let data: &'a S = self;
+        data.round_trip()
+    }
+}
+
+impl<'de,'a,S:?Sized,T> RoundTrip<'de, T> for Cow<'a,S> where
+    S: ToOwned + RoundTrip<'de, T>,
+    T: Deserialize<'de>,
+{
+    fn round_trip(&'de self) -> T { (**self).round_trip() }
+    fn round_trip_into(&'de self, target: &mut T) { (**self).round_trip_into(target) }
+}
+
+impl<'de,'a,S:?Sized,T> TryRoundTrip<'de, T> for Cow<'a,S> where
+    S: ToOwned + TryRoundTrip<'de, T>,
+    T: Deserialize<'de>,
+{
+    fn try_round_trip(&'de self) -> Result<T, RoundTripError> { (**self).try_round_trip() }
+}
+
+impl<'de,'a,S:?Sized,T,F> RoundTripIn<'de, F, T> for Cow<'a,S> where
+    F: Format,
+    S: ToOwned + RoundTripIn<'de, F, T>,
+    T: Deserialize<'de>,
+{
+    fn round_trip_in(&'de self, format: F) -> Result<T, RoundTripError> { (**self).round_trip_in(format) }
+}
+
+impl<'de,'a:'de,S:?Sized,T> IntoRoundTrip<'de, T> for Cow<'a,S> where
+    S: ToOwned + RoundTrip<'de, T>,
+    S::Owned: IntoRoundTrip<'de, T>,
+    T: Deserialize<'de>,
+{
+    fn into_round_trip(self) -> T {
+        match self {
+            Cow::Borrowed(data) => data.round_trip(),
+            Cow::Owned(data) => data.into_round_trip(),
+        }
+    }
+}
+
+impl<'de,'a,T:?Sized> SameDeserialization<'de> for Cow<'a,T> where
+    T: ToOwned,
+    T::Owned: SameDeserialization<'de>,
+{
+    type SameAs = <T::Owned as SameDeserialization<'de>>::SameAs;
+    fn from(data: Self::SameAs) -> Self { Cow::Owned(SameDeserialization::from(data)) }
+    fn as_same_mut(&mut self) -> Option<&mut Self::SameAs> {
+        match *self {
+            Cow::Owned(ref mut data) => data.as_same_mut(),
+            Cow::Borrowed(_) => None,
+        }
+    }
+}
+
+// Tuples, which serde only serializes up to length 16. Longer tuples implement neither
+// Serialize nor Deserialize, so there is nothing for them to round trip with.
+
+impl<'de, S0, T0, T> RoundTrip<'de, T> for (S0,) where
+    S0: RoundTrip<'de, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=(T0,)>,
+{
+    fn round_trip(&'de self) -> T { T::from((self.0.round_trip(),)) }
+}
+
+impl<'de, S0, T0, T> TryRoundTrip<'de, T> for (S0,) where
+    S0: TryRoundTrip<'de, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=(T0,)>,
+{
+    fn try_round_trip(&'de self) -> Result<T, RoundTripError> { Ok(T::from((self.0.try_round_trip()?,))) }
+}
+
+impl<'de,S0,T0,T,F> RoundTripIn<'de, F, T> for (S0,) where
+    F: Format,
+    S0: RoundTripIn<'de, F, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=(T0,)>,
+{
+    fn round_trip_in(&'de self, format: F) -> Result<T, RoundTripError> { Ok(T::from((self.0.round_trip_in(format)?,))) }
+}
+
+impl<'de, S0, T0, T> IntoRoundTrip<'de, T> for (S0,) where
+    S0: IntoRoundTrip<'de, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=(T0,)>,
+{
+    fn into_round_trip(self) -> T { T::from((self.0.into_round_trip(),)) }
+}
+
+impl<'de,T> SameDeserialization<'de> for (T,) where
+    T: Deserialize<'de>,
+{
+    type SameAs = (T,);
+    fn from(data: (T,)) -> (T,) { data }
+    fn as_same_mut(&mut self) -> Option<&mut (T,)> { Some(self) }
+}
+
+macro_rules! tuple_impls {
+    ($($xs:ident : $Ss:ident => $Ts:ident),*) => {
+        impl<'de,$($Ss),*,$($Ts),*,T> RoundTrip<'de, T> for ($($Ss),*) where
+            $($Ss: RoundTrip<'de, $Ts>),*,
+            $($Ts: Deserialize<'de>),*,
+            T: SameDeserialization<'de, SameAs=($($Ts),*)>,
+        {
+            fn round_trip(&'de self) -> T {
+                let ($(ref $xs),*) = *self;
+                T::from(($($xs.round_trip()),*))
+            }
+        }
+        impl<'de,$($Ss),*,$($Ts),*,T> TryRoundTrip<'de, T> for ($($Ss),*) where
+            $($Ss: TryRoundTrip<'de, $Ts>),*,
+            $($Ts: Deserialize<'de>),*,
+            T: SameDeserialization<'de, SameAs=($($Ts),*)>,
+        {
+            fn try_round_trip(&'de self) -> Result<T, RoundTripError> {
+                let ($(ref $xs),*) = *self;
+                Ok(T::from(($($xs.try_round_trip()?),*)))
+            }
+        }
+        impl<'de,$($Ss),*,$($Ts),*,T,F> RoundTripIn<'de, F, T> for ($($Ss),*) where
+            F: Format,
+            $($Ss: RoundTripIn<'de, F, $Ts>),*,
+            $($Ts: Deserialize<'de>),*,
+            T: SameDeserialization<'de, SameAs=($($Ts),*)>,
+        {
+            fn round_trip_in(&'de self, format: F) -> Result<T, RoundTripError> {
+                let ($(ref $xs),*) = *self;
+                Ok(T::from(($($xs.round_trip_in(format)?),*)))
+            }
+        }
+        impl<'de,$($Ss),*,$($Ts),*,T> IntoRoundTrip<'de, T> for ($($Ss),*) where
+            $($Ss: IntoRoundTrip<'de, $Ts>),*,
+            $($Ts: Deserialize<'de>),*,
+            T: SameDeserialization<'de, SameAs=($($Ts),*)>,
+        {
+            fn into_round_trip(self) -> T {
+                let ($($xs),*) = self;
+                T::from(($($xs.into_round_trip()),*))
+            }
+        }
+        impl<'de,$($Ts),*> SameDeserialization<'de> for ($($Ts),*) where
+            $($Ts: Deserialize<'de>),*,
+        {
+            type SameAs = ($($Ts),*);
+            fn from(data: ($($Ts),*)) -> ($($Ts),*) { data }
+            fn as_same_mut(&mut self) -> Option<&mut ($($Ts),*)> { Some(self) }
+        }
+    };
+}
+
+tuple_impls!(x_0: S0 => T0, x_1: S1 => T1);
+
+tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2);
+
+tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3);
+
+tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+             x_4: S4 => T4);
+
+tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+             x_4: S4 => T4, x_5: S5 => T5);
+
+tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+             x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6);
+
+tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+             x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7);
+
+tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+             x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
+             x_8: S8 => T8);
+
+tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+             x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
+             x_8: S8 => T8, x_9: S9 => T9);
+
+tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+             x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
+             x_8: S8 => T8, x_9: S9 => T9, x_a: SA => TA);
+
+tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+             x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
+             x_8: S8 => T8, x_9: S9 => T9, x_a: SA => TA, x_b: SB => TB);
+
+tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+             x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
+             x_8: S8 => T8, x_9: S9 => T9, x_a: SA => TA, x_b: SB => TB,
+             x_c: SC => TC);
+
+tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+             x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
+             x_8: S8 => T8, x_9: S9 => T9, x_a: SA => TA, x_b: SB => TB,
+             x_c: SC => TC, x_d: SD => TD);
+
+tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+             x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
+             x_8: S8 => T8, x_9: S9 => T9, x_a: SA => TA, x_b: SB => TB,
+             x_c: SC => TC, x_d: SD => TD, x_e: SE => TE);
+
+tuple_impls!(x_0: S0 => T0, x_1: S1 => T1, x_2: S2 => T2, x_3: S3 => T3,
+             x_4: S4 => T4, x_5: S5 => T5, x_6: S6 => T6, x_7: S7 => T7,
+             x_8: S8 => T8, x_9: S9 => T9, x_a: SA => TA, x_b: SB => TB,
+             x_c: SC => TC, x_d: SD => TD, x_e: SE => TE, x_f: SF => TF);
+
+// Phantom data
+
+impl<'de,S,T> RoundTrip<'de, T> for PhantomData<S> where
+    T: SameDeserialization<'de, SameAs=PhantomData<S>>,
+{
+    fn round_trip(&'de self) -> T { T::from(PhantomData) }
+}
+
+impl<'de,S,T> TryRoundTrip<'de, T> for PhantomData<S> where
+    T: SameDeserialization<'de, SameAs=PhantomData<S>>,
+{
+    fn try_round_trip(&'de self) -> Result<T, RoundTripError> { Ok(self.round_trip()) }
+}
+
+impl<'de,S,T,F> RoundTripIn<'de, F, T> for PhantomData<S> where
+    F: Format,
+    T: SameDeserialization<'de, SameAs=PhantomData<S>>,
+{
+    fn round_trip_in(&'de self, _: F) -> Result<T, RoundTripError> { self.try_round_trip() }
+}
+
+impl<'de,S,T> IntoRoundTrip<'de, T> for PhantomData<S> where
+    T: SameDeserialization<'de, SameAs=PhantomData<S>>,
+{
+    fn into_round_trip(self) -> T { T::from(PhantomData) }
+}
+
+impl<'de,T> SameDeserialization<'de> for PhantomData<T> {
+    type SameAs = PhantomData<T>;
+    fn from(data: PhantomData<T>) -> PhantomData<T> { data }
+    fn as_same_mut(&mut self) -> Option<&mut PhantomData<T>> { Some(self) }
+}
+
+// Options
+
+impl<'de,S0,T0,T> RoundTrip<'de, T> for Option<S0> where
+    S0: RoundTrip<'de, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=Option<T0>>,
+{
+    fn round_trip(&'de self) -> T { T::from(self.as_ref().map(RoundTrip::round_trip)) }
+    fn round_trip_into(&'de self, target: &mut T) {
+        match (self.as_ref(), target.as_same_mut()) {
+            (Some(x), Some(&mut Some(ref mut y))) => x.round_trip_into(y),
+            _ => *target = self.round_trip(),
+        }
+    }
+}
+
+impl<'de,S0,T0,T> TryRoundTrip<'de, T> for Option<S0> where
+    S0: TryRoundTrip<'de, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=Option<T0>>,
+{
+    fn try_round_trip(&'de self) -> Result<T, RoundTripError> {
+        match *self {
+            Some(ref x) => Ok(T::from(Some(x.try_round_trip()?))),
+            None => Ok(T::from(None)),
+        }
+    }
+}
+
+impl<'de,S0,T0,T,F> RoundTripIn<'de, F, T> for Option<S0> where
+    F: Format,
+    S0: RoundTripIn<'de, F, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=Option<T0>>,
+{
+    fn round_trip_in(&'de self, format: F) -> Result<T, RoundTripError> {
+        match *self {
+            Some(ref x) => Ok(T::from(Some(x.round_trip_in(format)?))),
+            None => Ok(T::from(None)),
+        }
+    }
+}
+
+impl<'de,S0,T0,T> IntoRoundTrip<'de, T> for Option<S0> where
+    S0: IntoRoundTrip<'de, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=Option<T0>>,
+{
+    fn into_round_trip(self) -> T { T::from(self.map(IntoRoundTrip::into_round_trip)) }
+}
+
+impl<'de,T> SameDeserialization<'de> for Option<T> where
+    T: Deserialize<'de>
+{
+    type SameAs = Option<T>;
+    fn from(data: Option<T>) -> Option<T> { data }
+    fn as_same_mut(&mut self) -> Option<&mut Option<T>> { Some(self) }
+}
+
+// Results
+
+impl<'de,S0,S1,T0,T1,T> RoundTrip<'de, T> for Result<S0,S1> where
+    S0: RoundTrip<'de, T0>,
+    S1: RoundTrip<'de, T1>,
+    T0: Deserialize<'de>,
+    T1: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=Result<T0,T1>>,
+{
+    fn round_trip(&'de self) -> T { T::from(self.as_ref().map(RoundTrip::round_trip).map_err(RoundTrip::round_trip)) }
+}
+
+impl<'de,S0,S1,T0,T1,T> TryRoundTrip<'de, T> for Result<S0,S1> where
+    S0: TryRoundTrip<'de, T0>,
+    S1: TryRoundTrip<'de, T1>,
+    T0: Deserialize<'de>,
+    T1: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=Result<T0,T1>>,
+{
+    fn try_round_trip(&'de self) -> Result<T, RoundTripError> {
+        match *self {
+            Ok(ref x) => Ok(T::from(Ok(x.try_round_trip()?))),
+            Err(ref x) => Ok(T::from(Err(x.try_round_trip()?))),
+        }
+    }
+}
+
+impl<'de,S0,S1,T0,T1,T,F> RoundTripIn<'de, F, T> for Result<S0,S1> where
+    F: Format,
+    S0: RoundTripIn<'de, F, T0>,
+    S1: RoundTripIn<'de, F, T1>,
+    T0: Deserialize<'de>,
+    T1: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=Result<T0,T1>>,
+{
+    fn round_trip_in(&'de self, format: F) -> Result<T, RoundTripError> {
+        match *self {
+            Ok(ref x) => Ok(T::from(Ok(x.round_trip_in(format)?))),
+            Err(ref x) => Ok(T::from(Err(x.round_trip_in(format)?))),
+        }
+    }
+}
+
+impl<'de,S0,S1,T0,T1,T> IntoRoundTrip<'de, T> for Result<S0,S1> where
+    S0: IntoRoundTrip<'de, T0>,
+    S1: IntoRoundTrip<'de, T1>,
+    T0: Deserialize<'de>,
+    T1: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=Result<T0,T1>>,
+{
+    fn into_round_trip(self) -> T { T::from(self.map(IntoRoundTrip::into_round_trip).map_err(IntoRoundTrip::into_round_trip)) }
+}
+
+impl<'de,T0,T1> SameDeserialization<'de> for Result<T0,T1> where
+    T0: Deserialize<'de>,
+    T1: Deserialize<'de>,
+{
+    type SameAs = Result<T0,T1>;
+    fn from(data: Result<T0,T1>) -> Result<T0,T1> { data }
+    fn as_same_mut(&mut self) -> Option<&mut Result<T0,T1>> { Some(self) }
+}
+
+// Wrappers, which serialize as their contents
+
+macro_rules! roundtrip_via_newtype {
+    ($F:ident) => {
+        impl<'de,S0,T0,T> RoundTrip<'de, T> for $F<S0> where
+            S0: RoundTrip<'de, T0>,
+            T0: Deserialize<'de>,
+            T: SameDeserialization<'de, SameAs=$F<T0>>,
+        {
+            fn round_trip(&'de self) -> T { T::from($F(self.0.round_trip())) }
+            fn round_trip_into(&'de self, target: &mut T) {
+                match target.as_same_mut() {
+                    Some(&mut $F(ref mut data)) => self.0.round_trip_into(data),
+                    None => *target = self.round_trip(),
+                }
+            }
+        }
+        impl<'de,S0,T0,T> TryRoundTrip<'de, T> for $F<S0> where
+            S0: TryRoundTrip<'de, T0>,
+            T0: Deserialize<'de>,
+            T: SameDeserialization<'de, SameAs=$F<T0>>,
+        {
+            fn try_round_trip(&'de self) -> Result<T, RoundTripError> { Ok(T::from($F(self.0.try_round_trip()?))) }
+        }
+        impl<'de,S0,T0,T,F> RoundTripIn<'de, F, T> for $F<S0> where
+            F: Format,
+            S0: RoundTripIn<'de, F, T0>,
+            T0: Deserialize<'de>,
+            T: SameDeserialization<'de, SameAs=$F<T0>>,
+        {
+            fn round_trip_in(&'de self, format: F) -> Result<T, RoundTripError> { Ok(T::from($F(self.0.round_trip_in(format)?))) }
+        }
+        impl<'de,S0,T0,T> IntoRoundTrip<'de, T> for $F<S0> where
+            S0: IntoRoundTrip<'de, T0>,
+            T0: Deserialize<'de>,
+            T: SameDeserialization<'de, SameAs=$F<T0>>,
+        {
+            fn into_round_trip(self) -> T { T::from($F(self.0.into_round_trip())) }
+        }
+        impl<'de,T0> SameDeserialization<'de> for $F<T0> where
+            T0: Deserialize<'de>,
+        {
+            type SameAs = $F<T0>;
+            fn from(data: $F<T0>) -> $F<T0> { data }
+            fn as_same_mut(&mut self) -> Option<&mut $F<T0>> { Some(self) }
+        }
+    };
+}
+
+roundtrip_via_newtype!(Reverse);
+roundtrip_via_newtype!(Wrapping);
+
+// Cells and locks, which serialize their contents while they are borrowed or locked.
+// The borrow only lasts for the round trip, so the target cannot borrow from the contents.
+// As in serde, a round trip fails if a `RefCell` is mutably borrowed or a lock is poisoned,
+// but `round_trip()` uses the contents of a poisoned lock regardless.
+
+macro_rules! roundtrip_via_guard {
+    ($F:ident $(: $bound:ident)*, $get:path, $try_get:path, $into_inner:path) => {
+        impl<'de,S0,T0,T> RoundTrip<'de, T> for $F<S0> where
+            S0: $($bound +)* for<'a> RoundTrip<'a, T0>,
+            T0: $($bound +)* DeserializeOwned,
+            T: SameDeserialization<'de, SameAs=$F<T0>>,
+        {
+            fn round_trip(&'de self) -> T { T::from($F::new($get(self).round_trip())) }
+        }
+        impl<'de,S0,T0,T> TryRoundTrip<'de, T> for $F<S0> where
+            S0: $($bound +)* for<'a> TryRoundTrip<'a, T0>,
+            T0: $($bound +)* DeserializeOwned,
+            T: SameDeserialization<'de, SameAs=$F<T0>>,
+        {
+            fn try_round_trip(&'de self) -> Result<T, RoundTripError> { Ok(T::from($F::new($try_get(self)?.try_round_trip()?))) }
+        }
+        impl<'de,S0,T0,T,F> RoundTripIn<'de, F, T> for $F<S0> where
+            F: Format,
+            S0: $($bound +)* for<'a> RoundTripIn<'a, F, T0>,
+            T0: $($bound +)* DeserializeOwned,
+            T: SameDeserialization<'de, SameAs=$F<T0>>,
+        {
+            fn round_trip_in(&'de self, format: F) -> Result<T, RoundTripError> { Ok(T::from($F::new($try_get(self)?.round_trip_in(format)?))) }
+        }
+        impl<'de,S0,T0,T> IntoRoundTrip<'de, T> for $F<S0> where
+            S0: $($bound +)* for<'a> IntoRoundTrip<'a, T0>,
+            T0: $($bound +)* DeserializeOwned,
+            T: SameDeserialization<'de, SameAs=$F<T0>>,
+        {
+            fn into_round_trip(self) -> T { T::from($F::new($into_inner(self).into_round_trip())) }
+        }
+        impl<'de,T0> SameDeserialization<'de> for $F<T0> where
+            T0: $($bound +)* Deserialize<'de>,
+        {
+            type SameAs = $F<T0>;
+            fn from(data: $F<T0>) -> $F<T0> { data }
+            fn as_same_mut(&mut self) -> Option<&mut $F<T0>> { Some(self) }
+        }
+    };
+}
+
+fn cell_get<S: Copy>(cell: &Cell<S>) -> S {
+    cell.get()
+}
+
+fn cell_try_get<S: Copy>(cell: &Cell<S>) -> Result<S, RoundTripError> {
+    Ok(cell.get())
+}
+
+fn ref_cell_try_get<S>(cell: &RefCell<S>) -> Result<Ref<'_, S>, RoundTripError> {
+    cell.try_borrow().map_err(|_| RoundTripError::new("already mutably borrowed"))
+}
+
+#[cfg(feature = "std")]
+fn mutex_get<S>(mutex: &Mutex<S>) -> MutexGuard<'_, S> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(feature = "std")]
+fn mutex_try_get<S>(mutex: &Mutex<S>) -> Result<MutexGuard<'_, S>, RoundTripError> {
+    mutex.lock().map_err(|_| RoundTripError::new("lock poison error while serializing"))
+}
+
+#[cfg(feature = "std")]
+fn mutex_into_inner<S>(mutex: Mutex<S>) -> S {
+    mutex.into_inner().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(feature = "std")]
+fn rw_lock_get<S>(lock: &RwLock<S>) -> RwLockReadGuard<'_, S> {
+    lock.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(feature = "std")]
+fn rw_lock_try_get<S>(lock: &RwLock<S>) -> Result<RwLockReadGuard<'_, S>, RoundTripError> {
+    lock.read().map_err(|_| RoundTripError::new("lock poison error while serializing"))
+}
+
+#[cfg(feature = "std")]
+fn rw_lock_into_inner<S>(lock: RwLock<S>) -> S {
+    lock.into_inner().unwrap_or_else(PoisonError::into_inner)
+}
+
+roundtrip_via_guard!(Cell: Copy, cell_get, cell_try_get, Cell::into_inner);
+#[cfg(feature = "std")]
+roundtrip_via_guard!(Mutex, mutex_get, mutex_try_get, mutex_into_inner);
+roundtrip_via_guard!(RefCell, RefCell::borrow, ref_cell_try_get, RefCell::into_inner);
+#[cfg(feature = "std")]
+roundtrip_via_guard!(RwLock, rw_lock_get, rw_lock_try_get, rw_lock_into_inner);
+
+// Atomics, which serialize the value they hold, loaded with relaxed ordering as in serde
+
+#[cfg(feature = "std")]
+macro_rules! roundtrip_via_load {
+    ($t:ident) => {
+        impl<'de,T> RoundTrip<'de, T> for $t
+            where T: SameDeserialization<'de, SameAs=$t>
+        {
+            fn round_trip(&'de self) -> T { T::from($t::new(self.load(Ordering::Relaxed))) }
+            fn round_trip_into(&'de self, target: &mut T) {
+                match target.as_same_mut() {
+                    Some(data) => data.store(self.load(Ordering::Relaxed), Ordering::Relaxed),
+                    None => *target = self.round_trip(),
+                }
+            }
+        }
+        impl<'de,T> TryRoundTrip<'de, T> for $t
+            where T: SameDeserialization<'de, SameAs=$t>
+        {
+            fn try_round_trip(&'de self) -> Result<T, RoundTripError> { Ok(self.round_trip()) }
+        }
+        impl<'de,T,F> RoundTripIn<'de, F, T> for $t
+            where F: Format, T: SameDeserialization<'de, SameAs=$t>
+        {
+            fn round_trip_in(&'de self, _: F) -> Result<T, RoundTripError> { self.try_round_trip() }
+        }
+        impl<'de,T> IntoRoundTrip<'de, T> for $t
+            where T: SameDeserialization<'de, SameAs=$t>
+        {
+            fn into_round_trip(self) -> T { T::from(self) }
+        }
+        impl<'de> SameDeserialization<'de> for $t {
+            type SameAs = $t;
+            fn from(data: $t) -> $t { data }
+            fn as_same_mut(&mut self) -> Option<&mut $t> { Some(self) }
+        }
+    };
+}
+
+#[cfg(all(feature = "std", target_has_atomic = "8"))]
+roundtrip_via_load!(AtomicBool);
+#[cfg(all(feature = "std", target_has_atomic = "16"))]
+roundtrip_via_load!(AtomicI16);
+#[cfg(all(feature = "std", target_has_atomic = "32"))]
+roundtrip_via_load!(AtomicI32);
+#[cfg(all(feature = "std", target_has_atomic = "64"))]
+roundtrip_via_load!(AtomicI64);
+#[cfg(all(feature = "std", target_has_atomic = "8"))]
+roundtrip_via_load!(AtomicI8);
+#[cfg(all(feature = "std", target_has_atomic = "ptr"))]
+roundtrip_via_load!(AtomicIsize);
+#[cfg(all(feature = "std", target_has_atomic = "16"))]
+roundtrip_via_load!(AtomicU16);
+#[cfg(all(feature = "std", target_has_atomic = "32"))]
+roundtrip_via_load!(AtomicU32);
+#[cfg(all(feature = "std", target_has_atomic = "64"))]
+roundtrip_via_load!(AtomicU64);
+#[cfg(all(feature = "std", target_has_atomic = "8"))]
+roundtrip_via_load!(AtomicU8);
+#[cfg(all(feature = "std", target_has_atomic = "ptr"))]
+roundtrip_via_load!(AtomicUsize);
+
+// Ranges, which serialize as structs of their bounds
+
+macro_rules! roundtrip_via_fields {
+    ($F:ident { $($xs:ident),* }) => {
+        impl<'de,S0,T0,T> RoundTrip<'de, T> for $F<S0> where
+            S0: RoundTrip<'de, T0>,
+            T0: Deserialize<'de>,
+            T: SameDeserialization<'de, SameAs=$F<T0>>,
+        {
+            fn round_trip(&'de self) -> T { T::from($F { $($xs: self.$xs.round_trip()),* }) }
+        }
+        impl<'de,S0,T0,T> TryRoundTrip<'de, T> for $F<S0> where
+            S0: TryRoundTrip<'de, T0>,
+            T0: Deserialize<'de>,
+            T: SameDeserialization<'de, SameAs=$F<T0>>,
+        {
+            fn try_round_trip(&'de self) -> Result<T, RoundTripError> { Ok(T::from($F { $($xs: self.$xs.try_round_trip()?),* })) }
+        }
+        impl<'de,S0,T0,T,F> RoundTripIn<'de, F, T> for $F<S0> where
+            F: Format,
+            S0: RoundTripIn<'de, F, T0>,
+            T0: Deserialize<'de>,
+            T: SameDeserialization<'de, SameAs=$F<T0>>,
+        {
+            fn round_trip_in(&'de self, format: F) -> Result<T, RoundTripError> { Ok(T::from($F { $($xs: self.$xs.round_trip_in(format)?),* })) }
+        }
+        impl<'de,S0,T0,T> IntoRoundTrip<'de, T> for $F<S0> where
+            S0: IntoRoundTrip<'de, T0>,
+            T0: Deserialize<'de>,
+            T: SameDeserialization<'de, SameAs=$F<T0>>,
+        {
+            fn into_round_trip(self) -> T { T::from($F { $($xs: self.$xs.into_round_trip()),* }) }
+        }
+        impl<'de,T0> SameDeserialization<'de> for $F<T0> where
+            T0: Deserialize<'de>,
+        {
+            type SameAs = $F<T0>;
+            fn from(data: $F<T0>) -> $F<T0> { data }
+            fn as_same_mut(&mut self) -> Option<&mut $F<T0>> { Some(self) }
+        }
+    };
+}
+
+roundtrip_via_fields!(Range { start, end });
+roundtrip_via_fields!(RangeFrom { start });
+roundtrip_via_fields!(RangeTo { end });
+
+// The bounds of an inclusive range are private, and an exhausted range is serialized
+// as its original bounds, so it round trips as a range which is not exhausted.
+
+impl<'de,S0,T0,T> RoundTrip<'de, T> for RangeInclusive<S0> where
+    S0: RoundTrip<'de, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=RangeInclusive<T0>>,
+{
+    fn round_trip(&'de self) -> T { T::from(RangeInclusive::new(self.start().round_trip(), self.end().round_trip())) }
+}
+
+impl<'de,S0,T0,T> TryRoundTrip<'de, T> for RangeInclusive<S0> where
+    S0: TryRoundTrip<'de, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=RangeInclusive<T0>>,
+{
+    fn try_round_trip(&'de self) -> Result<T, RoundTripError> {
+        Ok(T::from(RangeInclusive::new(self.start().try_round_trip()?, self.end().try_round_trip()?)))
+    }
+}
+
+impl<'de,S0,T0,T,F> RoundTripIn<'de, F, T> for RangeInclusive<S0> where
+    F: Format,
+    S0: RoundTripIn<'de, F, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=RangeInclusive<T0>>,
+{
+    fn round_trip_in(&'de self, format: F) -> Result<T, RoundTripError> {
+        Ok(T::from(RangeInclusive::new(self.start().round_trip_in(format)?, self.end().round_trip_in(format)?)))
+    }
+}
+
+impl<'de,S0,T0,T> IntoRoundTrip<'de, T> for RangeInclusive<S0> where
+    S0: IntoRoundTrip<'de, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=RangeInclusive<T0>>,
+{
+    fn into_round_trip(self) -> T {
+        let (start, end) = self.into_inner();
+        T::from(RangeInclusive::new(start.into_round_trip(), end.into_round_trip()))
+    }
+}
+
+impl<'de,T0> SameDeserialization<'de> for RangeInclusive<T0> where
+    T0: Deserialize<'de>,
+{
+    type SameAs = RangeInclusive<T0>;
+    fn from(data: RangeInclusive<T0>) -> RangeInclusive<T0> { data }
+    fn as_same_mut(&mut self) -> Option<&mut RangeInclusive<T0>> { Some(self) }
+}
+
+// Bounds
+
+impl<'de,S0,T0,T> RoundTrip<'de, T> for Bound<S0> where
+    S0: RoundTrip<'de, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=Bound<T0>>,
+{
+    fn round_trip(&'de self) -> T { T::from(self.as_ref().map(RoundTrip::round_trip)) }
+}
+
+impl<'de,S0,T0,T> TryRoundTrip<'de, T> for Bound<S0> where
+    S0: TryRoundTrip<'de, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=Bound<T0>>,
+{
+    fn try_round_trip(&'de self) -> Result<T, RoundTripError> {
+        match *self {
+            Bound::Included(ref x) => Ok(T::from(Bound::Included(x.try_round_trip()?))),
+            Bound::Excluded(ref x) => Ok(T::from(Bound::Excluded(x.try_round_trip()?))),
+            Bound::Unbounded => Ok(T::from(Bound::Unbounded)),
+        }
+    }
+}
+
+impl<'de,S0,T0,T,F> RoundTripIn<'de, F, T> for Bound<S0> where
+    F: Format,
+    S0: RoundTripIn<'de, F, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=Bound<T0>>,
+{
+    fn round_trip_in(&'de self, format: F) -> Result<T, RoundTripError> {
+        match *self {
+            Bound::Included(ref x) => Ok(T::from(Bound::Included(x.round_trip_in(format)?))),
+            Bound::Excluded(ref x) => Ok(T::from(Bound::Excluded(x.round_trip_in(format)?))),
+            Bound::Unbounded => Ok(T::from(Bound::Unbounded)),
+        }
+    }
+}
+
+impl<'de,S0,T0,T> IntoRoundTrip<'de, T> for Bound<S0> where
+    S0: IntoRoundTrip<'de, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=Bound<T0>>,
+{
+    fn into_round_trip(self) -> T { T::from(self.map(IntoRoundTrip::into_round_trip)) }
+}
+
+impl<'de,T0> SameDeserialization<'de> for Bound<T0> where
+    T0: Deserialize<'de>,
+{
+    type SameAs = Bound<T0>;
+    fn from(data: Bound<T0>) -> Bound<T0> { data }
+    fn as_same_mut(&mut self) -> Option<&mut Bound<T0>> { Some(self) }
+}
+
+// BinaryHeap
+
+impl<'de,S0,T0,T> RoundTrip<'de, T> for BinaryHeap<S0> where
+    S0: Ord+RoundTrip<'de, T0>,
+    T0: Ord+Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=BinaryHeap<T0>>,
+{
+    fn round_trip(&'de self) -> T { T::from(self.iter().map(RoundTrip::round_trip).collect()) }
+}
+
+impl<'de,S0,T0,T> TryRoundTrip<'de, T> for BinaryHeap<S0> where
+    S0: Ord+TryRoundTrip<'de, T0>,
+    T0: Ord+Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=BinaryHeap<T0>>,
+{
+    fn try_round_trip(&'de self) -> Result<T, RoundTripError> {
+        self.iter().map(TryRoundTrip::try_round_trip).collect::<Result<_,_>>().map(T::from)
+    }
+}
+
+impl<'de,S0,T0,T,F> RoundTripIn<'de, F, T> for BinaryHeap<S0> where
+    F: Format,
+    S0: Ord+RoundTripIn<'de, F, T0>,
+    T0: Ord+Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=BinaryHeap<T0>>,
+{
+    fn round_trip_in(&'de self, format: F) -> Result<T, RoundTripError> {
+        self.iter().map(|x| x.round_trip_in(format)).collect::<Result<_,_>>().map(T::from)
+    }
+}
+
+impl<'de,S0,T0,T> IntoRoundTrip<'de, T> for BinaryHeap<S0> where
+    S0: Ord+IntoRoundTrip<'de, T0>,
+    T0: Ord+Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=BinaryHeap<T0>>,
+{
+    fn into_round_trip(self) -> T { T::from(self.into_iter().map(IntoRoundTrip::into_round_trip).collect()) }
+}
+
+impl<'de,T0> SameDeserialization<'de> for BinaryHeap<T0> where
+    T0: Ord+Deserialize<'de>,
+{
+    type SameAs = BinaryHeap<T0>;
+    fn from(data: BinaryHeap<T0>) -> BinaryHeap<T0> { data }
+    fn as_same_mut(&mut self) -> Option<&mut BinaryHeap<T0>> { Some(self) }
+}
+
+// BTreeMap
+
+impl<'de,S0,S1,T0,T1,T> RoundTrip<'de, T> for BTreeMap<S0,S1> where
+    S0: Ord+RoundTrip<'de, T0>,
+    S1: RoundTrip<'de, T1>,
+    T0: Ord+Deserialize<'de>,
+    T1: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=BTreeMap<T0,T1>>,
+{
+    fn round_trip(&'de self) -> T {
+        T::from(self.iter().map(|(x1,x2)| (x1.round_trip(), x2.round_trip())).collect())
+    }
+    fn round_trip_into(&'de self, target: &mut T) {
+        match target.as_same_mut() {
+            Some(data) => {
+                data.clear();
+                data.extend(self.iter().map(|(x1,x2)| (x1.round_trip(), x2.round_trip())));
+            },
+            None => *target = self.round_trip(),
+        }
+    }
+}
+
+impl<'de,S0,S1,T0,T1,T> TryRoundTrip<'de, T> for BTreeMap<S0,S1> where
+    S0: Ord+TryRoundTrip<'de, T0>,
+    S1: TryRoundTrip<'de, T1>,
+    T0: Ord+Deserialize<'de>,
+    T1: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=BTreeMap<T0,T1>>,
+{
+    fn try_round_trip(&'de self) -> Result<T, RoundTripError> {
+        self.iter().map(|(x1,x2)| Ok((x1.try_round_trip()?, x2.try_round_trip()?))).collect::<Result<_,_>>().map(T::from)
+    }
+}
+
+impl<'de,S0,S1,T0,T1,T,F> RoundTripIn<'de, F, T> for BTreeMap<S0,S1> where
+    F: Format,
+    S0: Ord+RoundTripIn<'de, F, T0>,
+    S1: RoundTripIn<'de, F, T1>,
+    T0: Ord+Deserialize<'de>,
+    T1: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=BTreeMap<T0,T1>>,
+{
+    fn round_trip_in(&'de self, format: F) -> Result<T, RoundTripError> {
+        self.iter().map(|(x1,x2)| Ok((x1.round_trip_in(format)?, x2.round_trip_in(format)?))).collect::<Result<_,_>>().map(T::from)
+    }
+}
+
+impl<'de,S0,S1,T0,T1,T> IntoRoundTrip<'de, T> for BTreeMap<S0,S1> where
+    S0: Ord+IntoRoundTrip<'de, T0>,
+    S1: IntoRoundTrip<'de, T1>,
+    T0: Ord+Deserialize<'de>,
+    T1: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=BTreeMap<T0,T1>>,
+{
+    fn into_round_trip(self) -> T {
+        T::from(self.into_iter().map(|(x1,x2)| (x1.into_round_trip(), x2.into_round_trip())).collect())
+    }
+}
+
+impl<'de,T0,T1> SameDeserialization<'de> for BTreeMap<T0,T1> where
+    T0: Ord+Deserialize<'de>,
+    T1: Deserialize<'de>,
+{
+    type SameAs = BTreeMap<T0,T1>;
     fn from(data: BTreeMap<T0,T1>) -> BTreeMap<T0,T1> { data }
+    fn as_same_mut(&mut self) -> Option<&mut BTreeMap<T0,T1>> { Some(self) }
 }
 
 // BTreeSet
 
-impl<S0,T0,T> RoundTrip<T> for BTreeSet<S0> where
-    S0: Ord+RoundTrip<T0>,
-    T0: Ord+Deserialize,
-    T: SameDeserialization<SameAs=BTreeSet<T0>>,
+impl<'de,S0,T0,T> RoundTrip<'de, T> for BTreeSet<S0> where
+    S0: Ord+RoundTrip<'de, T0>,
+    T0: Ord+Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=BTreeSet<T0>>,
+{
+    fn round_trip(&'de self) -> T { T::from(self.iter().map(RoundTrip::round_trip).collect()) }
+}
+
+impl<'de,S0,T0,T> TryRoundTrip<'de, T> for BTreeSet<S0> where
+    S0: Ord+TryRoundTrip<'de, T0>,
+    T0: Ord+Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=BTreeSet<T0>>,
+{
+    fn try_round_trip(&'de self) -> Result<T, RoundTripError> {
+        self.iter().map(TryRoundTrip::try_round_trip).collect::<Result<_,_>>().map(T::from)
+    }
+}
+
+impl<'de,S0,T0,T,F> RoundTripIn<'de, F, T> for BTreeSet<S0> where
+    F: Format,
+    S0: Ord+RoundTripIn<'de, F, T0>,
+    T0: Ord+Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=BTreeSet<T0>>,
+{
+    fn round_trip_in(&'de self, format: F) -> Result<T, RoundTripError> {
+        self.iter().map(|x| x.round_trip_in(format)).collect::<Result<_,_>>().map(T::from)
+    }
+}
+
+impl<'de,S0,T0,T> IntoRoundTrip<'de, T> for BTreeSet<S0> where
+    S0: Ord+IntoRoundTrip<'de, T0>,
+    T0: Ord+Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=BTreeSet<T0>>,
 {
-    fn round_trip(&self) -> T { T::from(self.iter().map(RoundTrip::round_trip).collect()) }
+    fn into_round_trip(self) -> T { T::from(self.into_iter().map(IntoRoundTrip::into_round_trip).collect()) }
 }
 
-impl<T0> SameDeserialization for BTreeSet<T0> where
-    T0: Ord+Deserialize,
+impl<'de,T0> SameDeserialization<'de> for BTreeSet<T0> where
+    T0: Ord+Deserialize<'de>,
 {
     type SameAs = BTreeSet<T0>;
     fn from(data: BTreeSet<T0>) -> BTreeSet<T0> { data }
+    fn as_same_mut(&mut self) -> Option<&mut BTreeSet<T0>> { Some(self) }
 }
 
-// HashMap
+// HashMap, which can round trip to a map with a different hasher
 
-impl<S0,S1,T0,T1,H,T> RoundTrip<T> for HashMap<S0,S1,H> where
-    S0: Eq+Hash+RoundTrip<T0>,
-    S1: RoundTrip<T1>,
-    T0: Eq+Hash+Deserialize,
-    T1: Deserialize,
+#[cfg(feature = "std")]
+impl<'de,S0,S1,T0,T1,G,H,T> RoundTrip<'de, T> for HashMap<S0,S1,G> where
+    S0: Eq+Hash+RoundTrip<'de, T0>,
+    S1: RoundTrip<'de, T1>,
+    T0: Eq+Hash+Deserialize<'de>,
+    T1: Deserialize<'de>,
     H: BuildHasher+Default,
-    T: SameDeserialization<SameAs=HashMap<T0,T1,H>>,
+    T: SameDeserialization<'de, SameAs=HashMap<T0,T1,H>>,
 {
-    fn round_trip(&self) -> T {
+    fn round_trip(&'de self) -> T {
         T::from(self.iter().map(|(x1,x2)| (x1.round_trip(), x2.round_trip())).collect())
     }
+    fn round_trip_into(&'de self, target: &mut T) {
+        match target.as_same_mut() {
+            Some(data) => {
+                data.clear();
+                data.extend(self.iter().map(|(x1,x2)| (x1.round_trip(), x2.round_trip())));
+            },
+            None => *target = self.round_trip(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de,S0,S1,T0,T1,G,H,T> TryRoundTrip<'de, T> for HashMap<S0,S1,G> where
+    S0: Eq+Hash+TryRoundTrip<'de, T0>,
+    S1: TryRoundTrip<'de, T1>,
+    T0: Eq+Hash+Deserialize<'de>,
+    T1: Deserialize<'de>,
+    H: BuildHasher+Default,
+    T: SameDeserialization<'de, SameAs=HashMap<T0,T1,H>>,
+{
+    fn try_round_trip(&'de self) -> Result<T, RoundTripError> {
+        self.iter().map(|(x1,x2)| Ok((x1.try_round_trip()?, x2.try_round_trip()?))).collect::<Result<_,_>>().map(T::from)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de,S0,S1,T0,T1,G,H,T,F> RoundTripIn<'de, F, T> for HashMap<S0,S1,G> where
+    F: Format,
+    S0: Eq+Hash+RoundTripIn<'de, F, T0>,
+    S1: RoundTripIn<'de, F, T1>,
+    T0: Eq+Hash+Deserialize<'de>,
+    T1: Deserialize<'de>,
+    H: BuildHasher+Default,
+    T: SameDeserialization<'de, SameAs=HashMap<T0,T1,H>>,
+{
+    fn round_trip_in(&'de self, format: F) -> Result<T, RoundTripError> {
+        self.iter().map(|(x1,x2)| Ok((x1.round_trip_in(format)?, x2.round_trip_in(format)?))).collect::<Result<_,_>>().map(T::from)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de,S0,S1,T0,T1,G,H,T> IntoRoundTrip<'de, T> for HashMap<S0,S1,G> where
+    S0: Eq+Hash+IntoRoundTrip<'de, T0>,
+    S1: IntoRoundTrip<'de, T1>,
+    T0: Eq+Hash+Deserialize<'de>,
+    T1: Deserialize<'de>,
+    H: BuildHasher+Default,
+    T: SameDeserialization<'de, SameAs=HashMap<T0,T1,H>>,
+{
+    fn into_round_trip(self) -> T {
+        T::from(self.into_iter().map(|(x1,x2)| (x1.into_round_trip(), x2.into_round_trip())).collect())
+    }
 }
 
-impl<T0,T1,H> SameDeserialization for HashMap<T0,T1,H> where
-    T0: Eq+Hash+Deserialize,
-    T1: Deserialize,
+#[cfg(feature = "std")]
+impl<'de,T0,T1,H> SameDeserialization<'de> for HashMap<T0,T1,H> where
+    T0: Eq+Hash+Deserialize<'de>,
+    T1: Deserialize<'de>,
     H: BuildHasher+Default,
 {
     type SameAs = HashMap<T0,T1,H>;
     fn from(data: HashMap<T0,T1,H>) -> HashMap<T0,T1,H> { data }
+    fn as_same_mut(&mut self) -> Option<&mut HashMap<T0,T1,H>> { Some(self) }
 }
 
-// HashSet
+// HashSet, which can round trip to a set with a different hasher
 
-impl<S0,T0,H,T> RoundTrip<T> for HashSet<S0,H> where
-    S0: Eq+Hash+RoundTrip<T0>,
-    T0: Eq+Hash+Deserialize,
+#[cfg(feature = "std")]
+impl<'de,S0,T0,G,H,T> RoundTrip<'de, T> for HashSet<S0,G> where
+    S0: Eq+Hash+RoundTrip<'de, T0>,
+    T0: Eq+Hash+Deserialize<'de>,
     H: BuildHasher+Default,
-    T: SameDeserialization<SameAs=HashSet<T0,H>>,
+    T: SameDeserialization<'de, SameAs=HashSet<T0,H>>,
 {
-    fn round_trip(&self) -> T { T::from(self.iter().map(RoundTrip::round_trip).collect()) }
+    fn round_trip(&'de self) -> T { T::from(self.iter().map(RoundTrip::round_trip).collect()) }
 }
 
-impl<T0,H> SameDeserialization for HashSet<T0,H> where
-    T0: Eq+Hash+Deserialize,
+#[cfg(feature = "std")]
+impl<'de,S0,T0,G,H,T> TryRoundTrip<'de, T> for HashSet<S0,G> where
+    S0: Eq+Hash+TryRoundTrip<'de, T0>,
+    T0: Eq+Hash+Deserialize<'de>,
+    H: BuildHasher+Default,
+    T: SameDeserialization<'de, SameAs=HashSet<T0,H>>,
+{
+    fn try_round_trip(&'de self) -> Result<T, RoundTripError> {
+        self.iter().map(TryRoundTrip::try_round_trip).collect::<Result<_,_>>().map(T::from)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de,S0,T0,G,H,T,F> RoundTripIn<'de, F, T> for HashSet<S0,G> where
+    F: Format,
+    S0: Eq+Hash+RoundTripIn<'de, F, T0>,
+    T0: Eq+Hash+Deserialize<'de>,
+    H: BuildHasher+Default,
+    T: SameDeserialization<'de, SameAs=HashSet<T0,H>>,
+{
+    fn round_trip_in(&'de self, format: F) -> Result<T, RoundTripError> {
+        self.iter().map(|x| x.round_trip_in(format)).collect::<Result<_,_>>().map(T::from)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de,S0,T0,G,H,T> IntoRoundTrip<'de, T> for HashSet<S0,G> where
+    S0: Eq+Hash+IntoRoundTrip<'de, T0>,
+    T0: Eq+Hash+Deserialize<'de>,
+    H: BuildHasher+Default,
+    T: SameDeserialization<'de, SameAs=HashSet<T0,H>>,
+{
+    fn into_round_trip(self) -> T { T::from(self.into_iter().map(IntoRoundTrip::into_round_trip).collect()) }
+}
+
+#[cfg(feature = "std")]
+impl<'de,T0,H> SameDeserialization<'de> for HashSet<T0,H> where
+    T0: Eq+Hash+Deserialize<'de>,
     H: BuildHasher+Default,
 {
     type SameAs = HashSet<T0,H>;
     fn from(data: HashSet<T0,H>) -> HashSet<T0,H> { data }
+    fn as_same_mut(&mut self) -> Option<&mut HashSet<T0,H>> { Some(self) }
 }
 
 // LinkedList
 
-impl<S0,T0,T> RoundTrip<T> for LinkedList<S0> where
-    S0: RoundTrip<T0>,
-    T0: Deserialize,
-    T: SameDeserialization<SameAs=LinkedList<T0>>,
+impl<'de,S0,T0,T> RoundTrip<'de, T> for LinkedList<S0> where
+    S0: RoundTrip<'de, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=LinkedList<T0>>,
+{
+    fn round_trip(&'de self) -> T { T::from(self.iter().map(RoundTrip::round_trip).collect()) }
+}
+
+impl<'de,S0,T0,T> TryRoundTrip<'de, T> for LinkedList<S0> where
+    S0: TryRoundTrip<'de, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=LinkedList<T0>>,
+{
+    fn try_round_trip(&'de self) -> Result<T, RoundTripError> {
+        self.iter().map(TryRoundTrip::try_round_trip).collect::<Result<_,_>>().map(T::from)
+    }
+}
+
+impl<'de,S0,T0,T,F> RoundTripIn<'de, F, T> for LinkedList<S0> where
+    F: Format,
+    S0: RoundTripIn<'de, F, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=LinkedList<T0>>,
+{
+    fn round_trip_in(&'de self, format: F) -> Result<T, RoundTripError> {
+        self.iter().map(|x| x.round_trip_in(format)).collect::<Result<_,_>>().map(T::from)
+    }
+}
+
+impl<'de,S0,T0,T> IntoRoundTrip<'de, T> for LinkedList<S0> where
+    S0: IntoRoundTrip<'de, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=LinkedList<T0>>,
 {
-    fn round_trip(&self) -> T { T::from(self.iter().map(RoundTrip::round_trip).collect()) }
+    fn into_round_trip(self) -> T { T::from(self.into_iter().map(IntoRoundTrip::into_round_trip).collect()) }
 }
 
-impl<T0> SameDeserialization for LinkedList<T0> where
-    T0: Deserialize,
+impl<'de,T0> SameDeserialization<'de> for LinkedList<T0> where
+    T0: Deserialize<'de>,
 {
     type SameAs = LinkedList<T0>;
     fn from(data: LinkedList<T0>) -> LinkedList<T0> { data }
+    fn as_same_mut(&mut self) -> Option<&mut LinkedList<T0>> { Some(self) }
 }
 
 // VecDeque
 
-impl<S0,T0,T> RoundTrip<T> for VecDeque<S0> where
-    S0: RoundTrip<T0>,
-    T0: Deserialize,
-    T: SameDeserialization<SameAs=VecDeque<T0>>,
+impl<'de,S0,T0,T> RoundTrip<'de, T> for VecDeque<S0> where
+    S0: RoundTrip<'de, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=VecDeque<T0>>,
+{
+    fn round_trip(&'de self) -> T { T::from(self.iter().map(RoundTrip::round_trip).collect()) }
+    fn round_trip_into(&'de self, target: &mut T) {
+        match target.as_same_mut() {
+            Some(data) => {
+                data.truncate(self.len());
+                for (x, y) in self.iter().zip(data.iter_mut()) {
+                    x.round_trip_into(y);
+                }
+                let len = data.len();
+                data.extend(self.iter().skip(len).map(RoundTrip::round_trip));
+            },
+            None => *target = self.round_trip(),
+        }
+    }
+}
+
+impl<'de,S0,T0,T> TryRoundTrip<'de, T> for VecDeque<S0> where
+    S0: TryRoundTrip<'de, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=VecDeque<T0>>,
+{
+    fn try_round_trip(&'de self) -> Result<T, RoundTripError> {
+        self.iter().map(TryRoundTrip::try_round_trip).collect::<Result<_,_>>().map(T::from)
+    }
+}
+
+impl<'de,S0,T0,T,F> RoundTripIn<'de, F, T> for VecDeque<S0> where
+    F: Format,
+    S0: RoundTripIn<'de, F, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=VecDeque<T0>>,
+{
+    fn round_trip_in(&'de self, format: F) -> Result<T, RoundTripError> {
+        self.iter().map(|x| x.round_trip_in(format)).collect::<Result<_,_>>().map(T::from)
+    }
+}
+
+impl<'de,S0,T0,T> IntoRoundTrip<'de, T> for VecDeque<S0> where
+    S0: IntoRoundTrip<'de, T0>,
+    T0: Deserialize<'de>,
+    T: SameDeserialization<'de, SameAs=VecDeque<T0>>,
 {
-    fn round_trip(&self) -> T { T::from(self.iter().map(RoundTrip::round_trip).collect()) }
+    fn into_round_trip(self) -> T { T::from(self.into_iter().map(IntoRoundTrip::into_round_trip).collect()) }
 }
 
-impl<T0> SameDeserialization for VecDeque<T0> where
-    T0: Deserialize,
+impl<'de,T0> SameDeserialization<'de> for VecDeque<T0> where
+    T0: Deserialize<'de>,
 {
     type SameAs = VecDeque<T0>;
     fn from(data: VecDeque<T0>) -> VecDeque<T0> { data }
+    fn as_same_mut(&mut self) -> Option<&mut VecDeque<T0>> { Some(self) }
+}
+
+
+// Collections of different kinds, which serde serializes in the same way, so for example
+// a `Vec<S>` can be deserialized as a `VecDeque<T>`, or a `HashMap` as a `BTreeMap`.
+// As in serde's visitors, the elements are inserted in order, so a set keeps the first
+// of any equal elements, and a map keeps the first key and the last value.
+
+fn extend_in_order<C,T,I>(elements: I) -> C where
+    C: Default+Extend<T>,
+    I: Iterator<Item=T>,
+{
+    let mut result = C::default();
+    result.extend(elements);
+    result
+}
+
+fn try_extend_in_order<C,T,I>(elements: I) -> Result<C, RoundTripError> where
+    C: Default+Extend<T>,
+    I: Iterator<Item=Result<T, RoundTripError>>,
+{
+    let mut result = C::default();
+    for element in elements {
+        result.extend(Some(element?));
+    }
+    Ok(result)
+}
+
+macro_rules! roundtrip_seq_via_extend {
+    (ref impl<$($g:ident),*> $s:ty => $t:ty $(where $($bound:tt)*)*) => {
+        impl<'de,S0,T0,$($g,)*> RoundTrip<'de, $t> for $s where
+            S0: RoundTrip<'de, T0>,
+            T0: Deserialize<'de>,
+            $($($bound)*)*
+        {
+            fn round_trip(&'de self) -> $t { extend_in_order(self.iter().map(RoundTrip::round_trip)) }
+        }
+        impl<'de,S0,T0,$($g,)*> TryRoundTrip<'de, $t> for $s where
+            S0: TryRoundTrip<'de, T0>,
+            T0: Deserialize<'de>,
+            $($($bound)*)*
+        {
+            fn try_round_trip(&'de self) -> Result<$t, RoundTripError> {
+                try_extend_in_order(self.iter().map(TryRoundTrip::try_round_trip))
+            }
+        }
+        impl<'de,S0,T0,F,$($g,)*> RoundTripIn<'de, F, $t> for $s where
+            F: Format,
+            S0: RoundTripIn<'de, F, T0>,
+            T0: Deserialize<'de>,
+            $($($bound)*)*
+        {
+            fn round_trip_in(&'de self, format: F) -> Result<$t, RoundTripError> {
+                try_extend_in_order(self.iter().map(|x| x.round_trip_in(format)))
+            }
+        }
+    };
+    (impl<$($g:ident),*> $s:ty => $t:ty $(where $($bound:tt)*)*) => {
+        roundtrip_seq_via_extend!(ref impl<$($g),*> $s => $t $(where $($bound)*)*);
+        impl<'de,S0,T0,$($g,)*> IntoRoundTrip<'de, $t> for $s where
+            S0: IntoRoundTrip<'de, T0>,
+            T0: Deserialize<'de>,
+            $($($bound)*)*
+        {
+            fn into_round_trip(self) -> $t { extend_in_order(self.into_iter().map(IntoRoundTrip::into_round_trip)) }
+        }
+    };
+}
+
+#[cfg(any(feature = "std", feature = "indexmap"))]
+macro_rules! roundtrip_map_via_extend {
+    (impl<$($g:ident),*> $s:ty => $t:ty $(where $($bound:tt)*)*) => {
+        impl<'de,S0,S1,T0,T1,$($g,)*> RoundTrip<'de, $t> for $s where
+            S0: RoundTrip<'de, T0>,
+            S1: RoundTrip<'de, T1>,
+            T0: Deserialize<'de>,
+            T1: Deserialize<'de>,
+            $($($bound)*)*
+        {
+            fn round_trip(&'de self) -> $t {
+                extend_in_order(self.iter().map(|(x1,x2)| (x1.round_trip(), x2.round_trip())))
+            }
+        }
+        impl<'de,S0,S1,T0,T1,$($g,)*> TryRoundTrip<'de, $t> for $s where
+            S0: TryRoundTrip<'de, T0>,
+            S1: TryRoundTrip<'de, T1>,
+            T0: Deserialize<'de>,
+            T1: Deserialize<'de>,
+            $($($bound)*)*
+        {
+            fn try_round_trip(&'de self) -> Result<$t, RoundTripError> {
+                try_extend_in_order(self.iter().map(|(x1,x2)| Ok((x1.try_round_trip()?, x2.try_round_trip()?))))
+            }
+        }
+        impl<'de,S0,S1,T0,T1,F,$($g,)*> RoundTripIn<'de, F, $t> for $s where
+            F: Format,
+            S0: RoundTripIn<'de, F, T0>,
+            S1: RoundTripIn<'de, F, T1>,
+            T0: Deserialize<'de>,
+            T1: Deserialize<'de>,
+            $($($bound)*)*
+        {
+            fn round_trip_in(&'de self, format: F) -> Result<$t, RoundTripError> {
+                try_extend_in_order(self.iter().map(|(x1,x2)| Ok((x1.round_trip_in(format)?, x2.round_trip_in(format)?))))
+            }
+        }
+        impl<'de,S0,S1,T0,T1,$($g,)*> IntoRoundTrip<'de, $t> for $s where
+            S0: IntoRoundTrip<'de, T0>,
+            S1: IntoRoundTrip<'de, T1>,
+            T0: Deserialize<'de>,
+            T1: Deserialize<'de>,
+            $($($bound)*)*
+        {
+            fn into_round_trip(self) -> $t {
+                extend_in_order(self.into_iter().map(|(x1,x2)| (x1.into_round_trip(), x2.into_round_trip())))
+            }
+        }
+    };
+}
+
+roundtrip_seq_via_extend!(ref impl<> [S0] => BinaryHeap<T0> where T0: Ord);
+roundtrip_seq_via_extend!(ref impl<> [S0] => BTreeSet<T0> where T0: Ord);
+#[cfg(feature = "std")]
+roundtrip_seq_via_extend!(ref impl<H> [S0] => HashSet<T0,H> where T0: Eq+Hash, H: BuildHasher+Default);
+roundtrip_seq_via_extend!(ref impl<> [S0] => LinkedList<T0>);
+roundtrip_seq_via_extend!(ref impl<> [S0] => VecDeque<T0>);
+
+roundtrip_seq_via_extend!(impl<> BinaryHeap<S0> => BTreeSet<T0> where T0: Ord);
+#[cfg(feature = "std")]
+roundtrip_seq_via_extend!(impl<H> BinaryHeap<S0> => HashSet<T0,H> where T0: Eq+Hash, H: BuildHasher+Default);
+roundtrip_seq_via_extend!(impl<> BinaryHeap<S0> => LinkedList<T0>);
+roundtrip_seq_via_extend!(impl<> BinaryHeap<S0> => Vec<T0>);
+roundtrip_seq_via_extend!(impl<> BinaryHeap<S0> => VecDeque<T0>);
+
+roundtrip_seq_via_extend!(impl<> BTreeSet<S0> => BinaryHeap<T0> where T0: Ord);
+#[cfg(feature = "std")]
+roundtrip_seq_via_extend!(impl<H> BTreeSet<S0> => HashSet<T0,H> where T0: Eq+Hash, H: BuildHasher+Default);
+roundtrip_seq_via_extend!(impl<> BTreeSet<S0> => LinkedList<T0>);
+roundtrip_seq_via_extend!(impl<> BTreeSet<S0> => Vec<T0>);
+roundtrip_seq_via_extend!(impl<> BTreeSet<S0> => VecDeque<T0>);
+
+#[cfg(feature = "std")]
+roundtrip_seq_via_extend!(impl<G> HashSet<S0,G> => BinaryHeap<T0> where T0: Ord);
+#[cfg(feature = "std")]
+roundtrip_seq_via_extend!(impl<G> HashSet<S0,G> => BTreeSet<T0> where T0: Ord);
+#[cfg(feature = "std")]
+roundtrip_seq_via_extend!(impl<G> HashSet<S0,G> => LinkedList<T0>);
+#[cfg(feature = "std")]
+roundtrip_seq_via_extend!(impl<G> HashSet<S0,G> => Vec<T0>);
+#[cfg(feature = "std")]
+roundtrip_seq_via_extend!(impl<G> HashSet<S0,G> => VecDeque<T0>);
+
+roundtrip_seq_via_extend!(impl<> LinkedList<S0> => BinaryHeap<T0> where T0: Ord);
+roundtrip_seq_via_extend!(impl<> LinkedList<S0> => BTreeSet<T0> where T0: Ord);
+#[cfg(feature = "std")]
+roundtrip_seq_via_extend!(impl<H> LinkedList<S0> => HashSet<T0,H> where T0: Eq+Hash, H: BuildHasher+Default);
+roundtrip_seq_via_extend!(impl<> LinkedList<S0> => Vec<T0>);
+roundtrip_seq_via_extend!(impl<> LinkedList<S0> => VecDeque<T0>);
+
+roundtrip_seq_via_extend!(impl<> Vec<S0> => BinaryHeap<T0> where T0: Ord);
+roundtrip_seq_via_extend!(impl<> Vec<S0> => BTreeSet<T0> where T0: Ord);
+#[cfg(feature = "std")]
+roundtrip_seq_via_extend!(impl<H> Vec<S0> => HashSet<T0,H> where T0: Eq+Hash, H: BuildHasher+Default);
+roundtrip_seq_via_extend!(impl<> Vec<S0> => LinkedList<T0>);
+roundtrip_seq_via_extend!(impl<> Vec<S0> => VecDeque<T0>);
+
+roundtrip_seq_via_extend!(impl<> VecDeque<S0> => BinaryHeap<T0> where T0: Ord);
+roundtrip_seq_via_extend!(impl<> VecDeque<S0> => BTreeSet<T0> where T0: Ord);
+#[cfg(feature = "std")]
+roundtrip_seq_via_extend!(impl<H> VecDeque<S0> => HashSet<T0,H> where T0: Eq+Hash, H: BuildHasher+Default);
+roundtrip_seq_via_extend!(impl<> VecDeque<S0> => LinkedList<T0>);
+roundtrip_seq_via_extend!(impl<> VecDeque<S0> => Vec<T0>);
+
+#[cfg(feature = "std")]
+roundtrip_map_via_extend!(impl<H> BTreeMap<S0,S1> => HashMap<T0,T1,H> where T0: Eq+Hash, H: BuildHasher+Default);
+#[cfg(feature = "std")]
+roundtrip_map_via_extend!(impl<G> HashMap<S0,S1,G> => BTreeMap<T0,T1> where T0: Ord);
+
+// IndexMap, which keeps its entries in the order they were deserialized
+
+#[cfg(feature = "indexmap")]
+impl<'de,T0,T1,H> SameDeserialization<'de> for IndexMap<T0,T1,H> where
+    T0: Eq+Hash+Deserialize<'de>,
+    T1: Deserialize<'de>,
+    H: BuildHasher+Default,
+{
+    type SameAs = IndexMap<T0,T1,H>;
+    fn from(data: IndexMap<T0,T1,H>) -> IndexMap<T0,T1,H> { data }
+    fn as_same_mut(&mut self) -> Option<&mut IndexMap<T0,T1,H>> { Some(self) }
 }
 
+#[cfg(feature = "indexmap")]
+roundtrip_map_via_extend!(impl<H> BTreeMap<S0,S1> => IndexMap<T0,T1,H> where T0: Eq+Hash, H: BuildHasher+Default);
+#[cfg(all(feature = "indexmap", feature = "std"))]
+roundtrip_map_via_extend!(impl<G,H> HashMap<S0,S1,G> => IndexMap<T0,T1,H> where T0: Eq+Hash, H: BuildHasher+Default);
+#[cfg(feature = "indexmap")]
+roundtrip_map_via_extend!(impl<G> IndexMap<S0,S1,G> => BTreeMap<T0,T1> where T0: Ord);
+#[cfg(all(feature = "indexmap", feature = "std"))]
+roundtrip_map_via_extend!(impl<G,H> IndexMap<S0,S1,G> => HashMap<T0,T1,H> where T0: Eq+Hash, H: BuildHasher+Default);
+#[cfg(feature = "indexmap")]
+roundtrip_map_via_extend!(impl<G,H> IndexMap<S0,S1,G> => IndexMap<T0,T1,H> where T0: Eq+Hash, H: BuildHasher+Default);
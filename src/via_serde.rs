@@ -0,0 +1,1111 @@
+//! Round trips which really serialize then deserialize, via an in-memory content tree.
+//!
+//! This is a fallback for types which cannot implement `RoundTrip` by hand,
+//! for example third-party types which only implement `Serialize` and `Deserialize`.
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde::de;
+use serde::de::DeserializeOwned;
+use serde::de::DeserializeSeed;
+use serde::de::Unexpected;
+use serde::de::Visitor;
+use serde::ser;
+
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use core::fmt;
+
+use super::IntoRoundTrip;
+use super::RoundTrip;
+use super::RoundTripError;
+use super::RoundTripIn;
+use super::TryRoundTrip;
+use super::format::Format;
+
+/// Serialize then deserialize, via an in-memory content tree.
+pub fn round_trip<S, T>(data: &S) -> Result<T, RoundTripError> where
+    S: ?Sized + Serialize,
+    T: DeserializeOwned,
+{
+    from_content(to_content(data)?)
+}
+
+/// Serialize data as a content tree.
+pub fn to_content<S>(data: &S) -> Result<Content, RoundTripError> where
+    S: ?Sized + Serialize,
+{
+    data.serialize(Serializer)
+}
+
+/// Deserialize data from a content tree.
+pub fn from_content<T>(content: Content) -> Result<T, RoundTripError> where
+    T: DeserializeOwned,
+{
+    T::deserialize(Deserializer::new(content))
+}
+
+/// Serialize then deserialize using the given functions, via an in-memory content tree.
+///
+/// This is used by `derive(RoundTrip)` for fields with a `#[serde(with)]`,
+/// `#[serde(serialize_with)]` or `#[serde(deserialize_with)]` attribute,
+/// whose representation is up to the functions.
+pub fn round_trip_with<S, T, Ser, De>(data: &S, serialize: Ser, deserialize: De) -> Result<T, RoundTripError> where
+    S: ?Sized,
+    Ser: FnOnce(&S, Serializer) -> Result<Content, RoundTripError>,
+    De: FnOnce(Deserializer) -> Result<T, RoundTripError>,
+{
+    deserialize(Deserializer::new(serialize(data, Serializer)?))
+}
+
+/// Deserialize a struct field which is missing from the serialized data.
+///
+/// As in serde, this produces `None` for an optional field, and a missing field error otherwise.
+pub fn missing_field<'de, T>(field: &'static str) -> Result<T, RoundTripError> where
+    T: Deserialize<'de>,
+{
+    T::deserialize(MissingFieldDeserializer { field })
+}
+
+/// Check that data can be the content of a newtype variant of an internally tagged enum.
+///
+/// In an enum with a `#[serde(tag = "...")]` attribute, the tag is added to the content
+/// of a newtype variant, so serializing the variant fails unless its content is
+/// a struct, a map or an enum. This reports the same error as serde, without
+/// serializing anything but the outermost layer of the data.
+pub fn check_tagged_newtype<S>(data: &S, type_ident: &'static str, variant_ident: &'static str) -> Result<(), RoundTripError> where
+    S: ?Sized + Serialize,
+{
+    data.serialize(TaggedNewtypeChecker { type_ident, variant_ident })
+}
+
+/// A wrapper which round trips any serializable data via serde.
+///
+/// `ViaSerde(data)` implements `RoundTrip<'de, T>` for any `T: DeserializeOwned`,
+/// since the content tree is dropped after the round trip, so `T` cannot borrow from it.
+/// Since the round trip really serializes then deserializes,
+/// `round_trip()` panics if the round trip fails. Use `try_round_trip()`
+/// to get the error instead.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ViaSerde<S>(pub S);
+
+impl<S> Serialize for ViaSerde<S> where
+    S: Serialize,
+{
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> where
+        Ser: ser::Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de,S,T> RoundTrip<'de, T> for ViaSerde<S> where
+    S: Serialize,
+    T: DeserializeOwned,
+{
+    fn round_trip(&'de self) -> T {
+        match round_trip(&self.0) {
+            Ok(result) => result,
+            Err(err) => panic!("round trip via serde failed: {}", err),
+        }
+    }
+}
+
+impl<'de,S,T> TryRoundTrip<'de, T> for ViaSerde<S> where
+    S: Serialize,
+    T: DeserializeOwned,
+{
+    fn try_round_trip(&'de self) -> Result<T, RoundTripError> { round_trip(&self.0) }
+}
+
+// The in-memory format does not reproduce the lossy behaviour of any particular format.
+impl<'de,S,T,F> RoundTripIn<'de, F, T> for ViaSerde<S> where
+    S: Serialize,
+    T: DeserializeOwned,
+    F: Format,
+{
+    fn round_trip_in(&'de self, _: F) -> Result<T, RoundTripError> { round_trip(&self.0) }
+}
+
+impl<'de,S,T> IntoRoundTrip<'de, T> for ViaSerde<S> where
+    S: Serialize,
+    T: DeserializeOwned,
+{
+    fn into_round_trip(self) -> T {
+        match round_trip(&self.0) {
+            Ok(result) => result,
+            Err(err) => panic!("round trip via serde failed: {}", err),
+        }
+    }
+}
+
+/// The content of serialized data, as a tree.
+///
+/// This records everything in the serde data model, including the names
+/// of structs, fields and variants.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Content {
+    /// A `bool`.
+    Bool(bool),
+    /// An `i8`.
+    I8(i8),
+    /// An `i16`.
+    I16(i16),
+    /// An `i32`.
+    I32(i32),
+    /// An `i64`.
+    I64(i64),
+    /// An `i128`.
+    I128(i128),
+    /// A `u8`.
+    U8(u8),
+    /// A `u16`.
+    U16(u16),
+    /// A `u32`.
+    U32(u32),
+    /// A `u64`.
+    U64(u64),
+    /// A `u128`.
+    U128(u128),
+    /// An `f32`.
+    F32(f32),
+    /// An `f64`.
+    F64(f64),
+    /// A `char`.
+    Char(char),
+    /// A string.
+    String(String),
+    /// A byte array.
+    Bytes(Vec<u8>),
+    /// An absent optional value.
+    None,
+    /// A present optional value.
+    Some(Box<Content>),
+    /// The unit value.
+    Unit,
+    /// A unit struct, with its name.
+    UnitStruct(&'static str),
+    /// A newtype struct, with its name.
+    NewtypeStruct(&'static str, Box<Content>),
+    /// A sequence.
+    Seq(Vec<Content>),
+    /// A tuple, or a sequence of fixed size.
+    Tuple(Vec<Content>),
+    /// A tuple struct, with its name.
+    TupleStruct(&'static str, Vec<Content>),
+    /// A map.
+    Map(Vec<(Content, Content)>),
+    /// A struct, with its name and field names.
+    Struct(&'static str, Vec<(&'static str, Content)>),
+    /// A unit variant, with its enum name, variant index and variant name.
+    UnitVariant(&'static str, u32, &'static str),
+    /// A newtype variant, with its enum name, variant index and variant name.
+    NewtypeVariant(&'static str, u32, &'static str, Box<Content>),
+    /// A tuple variant, with its enum name, variant index and variant name.
+    TupleVariant(&'static str, u32, &'static str, Vec<Content>),
+    /// A struct variant, with its enum name, variant index, variant name and field names.
+    StructVariant(&'static str, u32, &'static str, Vec<(&'static str, Content)>),
+}
+
+impl Content {
+    fn unexpected(&self) -> Unexpected<'_> {
+        match *self {
+            Content::Bool(b) => Unexpected::Bool(b),
+            Content::I8(n) => Unexpected::Signed(n as i64),
+            Content::I16(n) => Unexpected::Signed(n as i64),
+            Content::I32(n) => Unexpected::Signed(n as i64),
+            Content::I64(n) => Unexpected::Signed(n),
+            Content::I128(_) => Unexpected::Other("i128"),
+            Content::U8(n) => Unexpected::Unsigned(n as u64),
+            Content::U16(n) => Unexpected::Unsigned(n as u64),
+            Content::U32(n) => Unexpected::Unsigned(n as u64),
+            Content::U64(n) => Unexpected::Unsigned(n),
+            Content::U128(_) => Unexpected::Other("u128"),
+            Content::F32(f) => Unexpected::Float(f as f64),
+            Content::F64(f) => Unexpected::Float(f),
+            Content::Char(c) => Unexpected::Char(c),
+            Content::String(ref s) => Unexpected::Str(s),
+            Content::Bytes(ref b) => Unexpected::Bytes(b),
+            Content::None | Content::Some(_) => Unexpected::Option,
+            Content::Unit | Content::UnitStruct(_) => Unexpected::Unit,
+            Content::NewtypeStruct(..) => Unexpected::NewtypeStruct,
+            Content::Seq(_) | Content::Tuple(_) | Content::TupleStruct(..) => Unexpected::Seq,
+            Content::Map(_) | Content::Struct(..) => Unexpected::Map,
+            Content::UnitVariant(..) => Unexpected::UnitVariant,
+            Content::NewtypeVariant(..) => Unexpected::NewtypeVariant,
+            Content::TupleVariant(..) => Unexpected::TupleVariant,
+            Content::StructVariant(..) => Unexpected::StructVariant,
+        }
+    }
+}
+
+impl Content {
+    /// The first difference between two content trees, if there is one.
+    ///
+    /// Map entries are matched by key rather than by position,
+    /// since maps such as `HashMap` may serialize their entries in any order.
+    pub fn difference(&self, other: &Content) -> Option<Difference> {
+        self.difference_at(other, &mut String::new())
+    }
+
+    fn difference_at(&self, other: &Content, path: &mut String) -> Option<Difference> {
+        match (self, other) {
+            (Content::Some(left), Content::Some(right)) => left.difference_at(right, path),
+            (Content::NewtypeStruct(left_name, left), Content::NewtypeStruct(right_name, right))
+                if left_name == right_name => within(path, ".0", |path| left.difference_at(right, path)),
+            (Content::Seq(left), Content::Seq(right)) => self.elements_difference(other, left, right, true, path),
+            (Content::Tuple(left), Content::Tuple(right)) => self.elements_difference(other, left, right, false, path),
+            (Content::TupleStruct(left_name, left), Content::TupleStruct(right_name, right))
+                if left_name == right_name => self.elements_difference(other, left, right, false, path),
+            (Content::Struct(left_name, left), Content::Struct(right_name, right))
+                if left_name == right_name => self.fields_difference(other, left, right, path),
+            (Content::Map(left), Content::Map(right)) => self.entries_difference(other, left, right, path),
+            (Content::NewtypeVariant(left_name, _, left_variant, left), Content::NewtypeVariant(right_name, _, right_variant, right))
+                if left_name == right_name && left_variant == right_variant =>
+                within(path, &format!(".{}", left_variant), |path| left.difference_at(right, path)),
+            (Content::TupleVariant(left_name, _, left_variant, left), Content::TupleVariant(right_name, _, right_variant, right))
+                if left_name == right_name && left_variant == right_variant =>
+                within(path, &format!(".{}", left_variant), |path| self.elements_difference(other, left, right, false, path)),
+            (Content::StructVariant(left_name, _, left_variant, left), Content::StructVariant(right_name, _, right_variant, right))
+                if left_name == right_name && left_variant == right_variant =>
+                within(path, &format!(".{}", left_variant), |path| self.fields_difference(other, left, right, path)),
+            _ if self == other => None,
+            _ => Some(Difference::new(path, self, other)),
+        }
+    }
+
+    fn elements_difference(&self, other: &Content, left: &[Content], right: &[Content], seq: bool, path: &mut String) -> Option<Difference> {
+        if left.len() != right.len() {
+            return Some(Difference::new(path, self, other));
+        }
+        left.iter().zip(right).enumerate()
+            .filter_map(|(index, (left, right))| {
+                let segment = if seq { format!("[{}]", index) } else { format!(".{}", index) };
+                within(path, &segment, |path| left.difference_at(right, path))
+            })
+            .next()
+    }
+
+    fn fields_difference(&self, other: &Content, left: &[(&'static str, Content)], right: &[(&'static str, Content)], path: &mut String) -> Option<Difference> {
+        if left.len() != right.len() || left.iter().zip(right).any(|(left, right)| left.0 != right.0) {
+            return Some(Difference::new(path, self, other));
+        }
+        left.iter().zip(right)
+            .filter_map(|(&(key, ref left), (_, right))| within(path, &format!(".{}", key), |path| left.difference_at(right, path)))
+            .next()
+    }
+
+    fn entries_difference(&self, other: &Content, left: &[(Content, Content)], right: &[(Content, Content)], path: &mut String) -> Option<Difference> {
+        if left.len() != right.len() {
+            return Some(Difference::new(path, self, other));
+        }
+        for (key, left) in left {
+            match right.iter().find(|entry| entry.0 == *key) {
+                Some((_, right)) => {
+                    let difference = within(path, &format!("[{}]", key_segment(key)), |path| left.difference_at(right, path));
+                    if difference.is_some() {
+                        return difference;
+                    }
+                },
+                None => return Some(Difference::new(path, self, other)),
+            }
+        }
+        None
+    }
+}
+
+fn within<F, R>(path: &mut String, segment: &str, f: F) -> R where
+    F: FnOnce(&mut String) -> R,
+{
+    let len = path.len();
+    path.push_str(segment);
+    let result = f(path);
+    path.truncate(len);
+    result
+}
+
+fn key_segment(key: &Content) -> String {
+    match *key {
+        Content::String(ref key) => format!("{:?}", key),
+        Content::Char(key) => format!("{:?}", key),
+        Content::Bool(key) => key.to_string(),
+        Content::I8(key) => key.to_string(),
+        Content::I16(key) => key.to_string(),
+        Content::I32(key) => key.to_string(),
+        Content::I64(key) => key.to_string(),
+        Content::I128(key) => key.to_string(),
+        Content::U8(key) => key.to_string(),
+        Content::U16(key) => key.to_string(),
+        Content::U32(key) => key.to_string(),
+        Content::U64(key) => key.to_string(),
+        Content::U128(key) => key.to_string(),
+        ref key => format!("{:?}", key),
+    }
+}
+
+/// The first difference between two content trees.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Difference {
+    /// The path to the difference, for example `.lines[2].Text.0`, where `Text` is an enum variant,
+    /// which is empty at the top level.
+    pub path: String,
+    /// The content on the left of the difference.
+    pub left: Content,
+    /// The content on the right of the difference.
+    pub right: Content,
+}
+
+impl Difference {
+    fn new(path: &str, left: &Content, right: &Content) -> Difference {
+        Difference { path: path.to_owned(), left: left.clone(), right: right.clone() }
+    }
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(formatter, "{:?} != {:?} at the top level", self.left, self.right)
+        } else {
+            write!(formatter, "{:?} != {:?} at {}", self.left, self.right, self.path)
+        }
+    }
+}
+
+// Serializing a content tree replays the serializer calls which produced it
+
+impl Serialize for Content {
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> where
+        Ser: ser::Serializer,
+    {
+        match *self {
+            Content::Bool(v) => serializer.serialize_bool(v),
+            Content::I8(v) => serializer.serialize_i8(v),
+            Content::I16(v) => serializer.serialize_i16(v),
+            Content::I32(v) => serializer.serialize_i32(v),
+            Content::I64(v) => serializer.serialize_i64(v),
+            Content::I128(v) => serializer.serialize_i128(v),
+            Content::U8(v) => serializer.serialize_u8(v),
+            Content::U16(v) => serializer.serialize_u16(v),
+            Content::U32(v) => serializer.serialize_u32(v),
+            Content::U64(v) => serializer.serialize_u64(v),
+            Content::U128(v) => serializer.serialize_u128(v),
+            Content::F32(v) => serializer.serialize_f32(v),
+            Content::F64(v) => serializer.serialize_f64(v),
+            Content::Char(v) => serializer.serialize_char(v),
+            Content::String(ref v) => serializer.serialize_str(v),
+            Content::Bytes(ref v) => serializer.serialize_bytes(v),
+            Content::None => serializer.serialize_none(),
+            Content::Some(ref v) => serializer.serialize_some(&**v),
+            Content::Unit => serializer.serialize_unit(),
+            Content::UnitStruct(name) => serializer.serialize_unit_struct(name),
+            Content::NewtypeStruct(name, ref v) => serializer.serialize_newtype_struct(name, &**v),
+            Content::Seq(ref v) => {
+                let mut state = serializer.serialize_seq(Some(v.len()))?;
+                for element in v {
+                    ser::SerializeSeq::serialize_element(&mut state, element)?;
+                }
+                ser::SerializeSeq::end(state)
+            },
+            Content::Tuple(ref v) => {
+                let mut state = serializer.serialize_tuple(v.len())?;
+                for element in v {
+                    ser::SerializeTuple::serialize_element(&mut state, element)?;
+                }
+                ser::SerializeTuple::end(state)
+            },
+            Content::TupleStruct(name, ref v) => {
+                let mut state = serializer.serialize_tuple_struct(name, v.len())?;
+                for field in v {
+                    ser::SerializeTupleStruct::serialize_field(&mut state, field)?;
+                }
+                ser::SerializeTupleStruct::end(state)
+            },
+            Content::Map(ref v) => {
+                let mut state = serializer.serialize_map(Some(v.len()))?;
+                for (key, value) in v {
+                    ser::SerializeMap::serialize_key(&mut state, key)?;
+                    ser::SerializeMap::serialize_value(&mut state, value)?;
+                }
+                ser::SerializeMap::end(state)
+            },
+            Content::Struct(name, ref v) => {
+                let mut state = serializer.serialize_struct(name, v.len())?;
+                for &(key, ref value) in v {
+                    ser::SerializeStruct::serialize_field(&mut state, key, value)?;
+                }
+                ser::SerializeStruct::end(state)
+            },
+            Content::UnitVariant(name, index, variant) => serializer.serialize_unit_variant(name, index, variant),
+            Content::NewtypeVariant(name, index, variant, ref v) => serializer.serialize_newtype_variant(name, index, variant, &**v),
+            Content::TupleVariant(name, index, variant, ref v) => {
+                let mut state = serializer.serialize_tuple_variant(name, index, variant, v.len())?;
+                for field in v {
+                    ser::SerializeTupleVariant::serialize_field(&mut state, field)?;
+                }
+                ser::SerializeTupleVariant::end(state)
+            },
+            Content::StructVariant(name, index, variant, ref v) => {
+                let mut state = serializer.serialize_struct_variant(name, index, variant, v.len())?;
+                for &(key, ref value) in v {
+                    ser::SerializeStructVariant::serialize_field(&mut state, key, value)?;
+                }
+                ser::SerializeStructVariant::end(state)
+            },
+        }
+    }
+}
+
+// Serializing to a content tree
+
+/// A serializer which produces a content tree.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Content;
+    type Error = RoundTripError;
+    type SerializeSeq = SerializeSeq;
+    type SerializeTuple = SerializeSeq;
+    type SerializeTupleStruct = SerializeSeq;
+    type SerializeTupleVariant = SerializeSeq;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeStruct;
+    type SerializeStructVariant = SerializeStruct;
+
+    fn serialize_bool(self, v: bool) -> Result<Content, RoundTripError> { Ok(Content::Bool(v)) }
+    fn serialize_i8(self, v: i8) -> Result<Content, RoundTripError> { Ok(Content::I8(v)) }
+    fn serialize_i16(self, v: i16) -> Result<Content, RoundTripError> { Ok(Content::I16(v)) }
+    fn serialize_i32(self, v: i32) -> Result<Content, RoundTripError> { Ok(Content::I32(v)) }
+    fn serialize_i64(self, v: i64) -> Result<Content, RoundTripError> { Ok(Content::I64(v)) }
+    fn serialize_i128(self, v: i128) -> Result<Content, RoundTripError> { Ok(Content::I128(v)) }
+    fn serialize_u8(self, v: u8) -> Result<Content, RoundTripError> { Ok(Content::U8(v)) }
+    fn serialize_u16(self, v: u16) -> Result<Content, RoundTripError> { Ok(Content::U16(v)) }
+    fn serialize_u32(self, v: u32) -> Result<Content, RoundTripError> { Ok(Content::U32(v)) }
+    fn serialize_u64(self, v: u64) -> Result<Content, RoundTripError> { Ok(Content::U64(v)) }
+    fn serialize_u128(self, v: u128) -> Result<Content, RoundTripError> { Ok(Content::U128(v)) }
+    fn serialize_f32(self, v: f32) -> Result<Content, RoundTripError> { Ok(Content::F32(v)) }
+    fn serialize_f64(self, v: f64) -> Result<Content, RoundTripError> { Ok(Content::F64(v)) }
+    fn serialize_char(self, v: char) -> Result<Content, RoundTripError> { Ok(Content::Char(v)) }
+    fn serialize_str(self, v: &str) -> Result<Content, RoundTripError> { Ok(Content::String(v.to_owned())) }
+    fn serialize_bytes(self, v: &[u8]) -> Result<Content, RoundTripError> { Ok(Content::Bytes(v.to_owned())) }
+    fn serialize_none(self) -> Result<Content, RoundTripError> { Ok(Content::None) }
+    fn serialize_some<S: ?Sized + Serialize>(self, value: &S) -> Result<Content, RoundTripError> {
+        Ok(Content::Some(Box::new(to_content(value)?)))
+    }
+    fn serialize_unit(self) -> Result<Content, RoundTripError> { Ok(Content::Unit) }
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Content, RoundTripError> {
+        Ok(Content::UnitStruct(name))
+    }
+    fn serialize_unit_variant(self, name: &'static str, index: u32, variant: &'static str) -> Result<Content, RoundTripError> {
+        Ok(Content::UnitVariant(name, index, variant))
+    }
+    fn serialize_newtype_struct<S: ?Sized + Serialize>(self, name: &'static str, value: &S) -> Result<Content, RoundTripError> {
+        Ok(Content::NewtypeStruct(name, Box::new(to_content(value)?)))
+    }
+    fn serialize_newtype_variant<S: ?Sized + Serialize>(self, name: &'static str, index: u32, variant: &'static str, value: &S) -> Result<Content, RoundTripError> {
+        Ok(Content::NewtypeVariant(name, index, variant, Box::new(to_content(value)?)))
+    }
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeSeq, RoundTripError> {
+        Ok(SerializeSeq::new(SeqKind::Seq, len.unwrap_or(0)))
+    }
+    fn serialize_tuple(self, len: usize) -> Result<SerializeSeq, RoundTripError> {
+        Ok(SerializeSeq::new(SeqKind::Tuple, len))
+    }
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<SerializeSeq, RoundTripError> {
+        Ok(SerializeSeq::new(SeqKind::TupleStruct(name), len))
+    }
+    fn serialize_tuple_variant(self, name: &'static str, index: u32, variant: &'static str, len: usize) -> Result<SerializeSeq, RoundTripError> {
+        Ok(SerializeSeq::new(SeqKind::TupleVariant(name, index, variant), len))
+    }
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeMap, RoundTripError> {
+        Ok(SerializeMap { entries: Vec::with_capacity(len.unwrap_or(0)), key: None })
+    }
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<SerializeStruct, RoundTripError> {
+        Ok(SerializeStruct { kind: StructKind::Struct(name), fields: Vec::with_capacity(len) })
+    }
+    fn serialize_struct_variant(self, name: &'static str, index: u32, variant: &'static str, len: usize) -> Result<SerializeStruct, RoundTripError> {
+        Ok(SerializeStruct { kind: StructKind::StructVariant(name, index, variant), fields: Vec::with_capacity(len) })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum SeqKind {
+    Seq,
+    Tuple,
+    TupleStruct(&'static str),
+    TupleVariant(&'static str, u32, &'static str),
+}
+
+/// Serialize the elements of a sequence, tuple, tuple struct or tuple variant.
+#[derive(Debug)]
+pub struct SerializeSeq {
+    kind: SeqKind,
+    elements: Vec<Content>,
+}
+
+impl SerializeSeq {
+    fn new(kind: SeqKind, len: usize) -> SerializeSeq {
+        SerializeSeq { kind, elements: Vec::with_capacity(len) }
+    }
+    fn push<S: ?Sized + Serialize>(&mut self, value: &S) -> Result<(), RoundTripError> {
+        self.elements.push(to_content(value)?);
+        Ok(())
+    }
+    fn finish(self) -> Result<Content, RoundTripError> {
+        Ok(match self.kind {
+            SeqKind::Seq => Content::Seq(self.elements),
+            SeqKind::Tuple => Content::Tuple(self.elements),
+            SeqKind::TupleStruct(name) => Content::TupleStruct(name, self.elements),
+            SeqKind::TupleVariant(name, index, variant) => Content::TupleVariant(name, index, variant, self.elements),
+        })
+    }
+}
+
+impl ser::SerializeSeq for SerializeSeq {
+    type Ok = Content;
+    type Error = RoundTripError;
+    fn serialize_element<S: ?Sized + Serialize>(&mut self, value: &S) -> Result<(), RoundTripError> { self.push(value) }
+    fn end(self) -> Result<Content, RoundTripError> { self.finish() }
+}
+
+impl ser::SerializeTuple for SerializeSeq {
+    type Ok = Content;
+    type Error = RoundTripError;
+    fn serialize_element<S: ?Sized + Serialize>(&mut self, value: &S) -> Result<(), RoundTripError> { self.push(value) }
+    fn end(self) -> Result<Content, RoundTripError> { self.finish() }
+}
+
+impl ser::SerializeTupleStruct for SerializeSeq {
+    type Ok = Content;
+    type Error = RoundTripError;
+    fn serialize_field<S: ?Sized + Serialize>(&mut self, value: &S) -> Result<(), RoundTripError> { self.push(value) }
+    fn end(self) -> Result<Content, RoundTripError> { self.finish() }
+}
+
+impl ser::SerializeTupleVariant for SerializeSeq {
+    type Ok = Content;
+    type Error = RoundTripError;
+    fn serialize_field<S: ?Sized + Serialize>(&mut self, value: &S) -> Result<(), RoundTripError> { self.push(value) }
+    fn end(self) -> Result<Content, RoundTripError> { self.finish() }
+}
+
+/// Serialize the entries of a map.
+#[derive(Debug)]
+pub struct SerializeMap {
+    entries: Vec<(Content, Content)>,
+    key: Option<Content>,
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Content;
+    type Error = RoundTripError;
+    fn serialize_key<S: ?Sized + Serialize>(&mut self, key: &S) -> Result<(), RoundTripError> {
+        self.key = Some(to_content(key)?);
+        Ok(())
+    }
+    fn serialize_value<S: ?Sized + Serialize>(&mut self, value: &S) -> Result<(), RoundTripError> {
+        let key = self.key.take().ok_or_else(|| RoundTripError::new("map value serialized before its key"))?;
+        self.entries.push((key, to_content(value)?));
+        Ok(())
+    }
+    fn end(self) -> Result<Content, RoundTripError> { Ok(Content::Map(self.entries)) }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum StructKind {
+    Struct(&'static str),
+    StructVariant(&'static str, u32, &'static str),
+}
+
+/// Serialize the fields of a struct or struct variant.
+#[derive(Debug)]
+pub struct SerializeStruct {
+    kind: StructKind,
+    fields: Vec<(&'static str, Content)>,
+}
+
+impl SerializeStruct {
+    fn push<S: ?Sized + Serialize>(&mut self, key: &'static str, value: &S) -> Result<(), RoundTripError> {
+        self.fields.push((key, to_content(value)?));
+        Ok(())
+    }
+    fn finish(self) -> Result<Content, RoundTripError> {
+        Ok(match self.kind {
+            StructKind::Struct(name) => Content::Struct(name, self.fields),
+            StructKind::StructVariant(name, index, variant) => Content::StructVariant(name, index, variant, self.fields),
+        })
+    }
+}
+
+impl ser::SerializeStruct for SerializeStruct {
+    type Ok = Content;
+    type Error = RoundTripError;
+    fn serialize_field<S: ?Sized + Serialize>(&mut self, key: &'static str, value: &S) -> Result<(), RoundTripError> { self.push(key, value) }
+    fn end(self) -> Result<Content, RoundTripError> { self.finish() }
+}
+
+impl ser::SerializeStructVariant for SerializeStruct {
+    type Ok = Content;
+    type Error = RoundTripError;
+    fn serialize_field<S: ?Sized + Serialize>(&mut self, key: &'static str, value: &S) -> Result<(), RoundTripError> { self.push(key, value) }
+    fn end(self) -> Result<Content, RoundTripError> { self.finish() }
+}
+
+// Checking the content of a tagged newtype variant
+
+#[derive(Clone, Copy, Debug)]
+struct TaggedNewtypeChecker {
+    type_ident: &'static str,
+    variant_ident: &'static str,
+}
+
+impl TaggedNewtypeChecker {
+    fn bad_type(self, what: &str) -> RoundTripError {
+        RoundTripError::new(format_args!("cannot serialize tagged newtype variant {}::{} containing {}", self.type_ident, self.variant_ident, what))
+    }
+}
+
+impl ser::Serializer for TaggedNewtypeChecker {
+    type Ok = ();
+    type Error = RoundTripError;
+    type SerializeSeq = ser::Impossible<(), RoundTripError>;
+    type SerializeTuple = ser::Impossible<(), RoundTripError>;
+    type SerializeTupleStruct = ser::Impossible<(), RoundTripError>;
+    type SerializeTupleVariant = IgnoreContents;
+    type SerializeMap = IgnoreContents;
+    type SerializeStruct = IgnoreContents;
+    type SerializeStructVariant = IgnoreContents;
+
+    fn serialize_bool(self, _: bool) -> Result<(), RoundTripError> { Err(self.bad_type("a boolean")) }
+    fn serialize_i8(self, _: i8) -> Result<(), RoundTripError> { Err(self.bad_type("an integer")) }
+    fn serialize_i16(self, _: i16) -> Result<(), RoundTripError> { Err(self.bad_type("an integer")) }
+    fn serialize_i32(self, _: i32) -> Result<(), RoundTripError> { Err(self.bad_type("an integer")) }
+    fn serialize_i64(self, _: i64) -> Result<(), RoundTripError> { Err(self.bad_type("an integer")) }
+    fn serialize_i128(self, _: i128) -> Result<(), RoundTripError> { Err(self.bad_type("an integer")) }
+    fn serialize_u8(self, _: u8) -> Result<(), RoundTripError> { Err(self.bad_type("an integer")) }
+    fn serialize_u16(self, _: u16) -> Result<(), RoundTripError> { Err(self.bad_type("an integer")) }
+    fn serialize_u32(self, _: u32) -> Result<(), RoundTripError> { Err(self.bad_type("an integer")) }
+    fn serialize_u64(self, _: u64) -> Result<(), RoundTripError> { Err(self.bad_type("an integer")) }
+    fn serialize_u128(self, _: u128) -> Result<(), RoundTripError> { Err(self.bad_type("an integer")) }
+    fn serialize_f32(self, _: f32) -> Result<(), RoundTripError> { Err(self.bad_type("a float")) }
+    fn serialize_f64(self, _: f64) -> Result<(), RoundTripError> { Err(self.bad_type("a float")) }
+    fn serialize_char(self, _: char) -> Result<(), RoundTripError> { Err(self.bad_type("a char")) }
+    fn serialize_str(self, _: &str) -> Result<(), RoundTripError> { Err(self.bad_type("a string")) }
+    fn serialize_bytes(self, _: &[u8]) -> Result<(), RoundTripError> { Err(self.bad_type("a byte array")) }
+    fn serialize_none(self) -> Result<(), RoundTripError> { Err(self.bad_type("an optional")) }
+    fn serialize_some<S: ?Sized + Serialize>(self, _: &S) -> Result<(), RoundTripError> { Err(self.bad_type("an optional")) }
+    fn serialize_unit(self) -> Result<(), RoundTripError> { Ok(()) }
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), RoundTripError> { Ok(()) }
+    fn serialize_unit_variant(self, _: &'static str, _: u32, _: &'static str) -> Result<(), RoundTripError> { Ok(()) }
+    fn serialize_newtype_struct<S: ?Sized + Serialize>(self, _: &'static str, value: &S) -> Result<(), RoundTripError> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<S: ?Sized + Serialize>(self, _: &'static str, _: u32, _: &'static str, _: &S) -> Result<(), RoundTripError> {
+        Ok(())
+    }
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, RoundTripError> { Err(self.bad_type("a sequence")) }
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, RoundTripError> { Err(self.bad_type("a tuple")) }
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeTupleStruct, RoundTripError> {
+        Err(self.bad_type("a tuple struct"))
+    }
+    fn serialize_tuple_variant(self, _: &'static str, _: u32, _: &'static str, _: usize) -> Result<IgnoreContents, RoundTripError> {
+        Ok(IgnoreContents)
+    }
+    fn serialize_map(self, _: Option<usize>) -> Result<IgnoreContents, RoundTripError> { Ok(IgnoreContents) }
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<IgnoreContents, RoundTripError> { Ok(IgnoreContents) }
+    fn serialize_struct_variant(self, _: &'static str, _: u32, _: &'static str, _: usize) -> Result<IgnoreContents, RoundTripError> {
+        Ok(IgnoreContents)
+    }
+}
+
+// The contents of a tagged newtype variant are checked by their own round trip.
+struct IgnoreContents;
+
+impl ser::SerializeTupleVariant for IgnoreContents {
+    type Ok = ();
+    type Error = RoundTripError;
+    fn serialize_field<S: ?Sized + Serialize>(&mut self, _: &S) -> Result<(), RoundTripError> { Ok(()) }
+    fn end(self) -> Result<(), RoundTripError> { Ok(()) }
+}
+
+impl ser::SerializeMap for IgnoreContents {
+    type Ok = ();
+    type Error = RoundTripError;
+    fn serialize_key<S: ?Sized + Serialize>(&mut self, _: &S) -> Result<(), RoundTripError> { Ok(()) }
+    fn serialize_value<S: ?Sized + Serialize>(&mut self, _: &S) -> Result<(), RoundTripError> { Ok(()) }
+    fn end(self) -> Result<(), RoundTripError> { Ok(()) }
+}
+
+impl ser::SerializeStruct for IgnoreContents {
+    type Ok = ();
+    type Error = RoundTripError;
+    fn serialize_field<S: ?Sized + Serialize>(&mut self, _: &'static str, _: &S) -> Result<(), RoundTripError> { Ok(()) }
+    fn end(self) -> Result<(), RoundTripError> { Ok(()) }
+}
+
+impl ser::SerializeStructVariant for IgnoreContents {
+    type Ok = ();
+    type Error = RoundTripError;
+    fn serialize_field<S: ?Sized + Serialize>(&mut self, _: &'static str, _: &S) -> Result<(), RoundTripError> { Ok(()) }
+    fn end(self) -> Result<(), RoundTripError> { Ok(()) }
+}
+
+// Deserializing from a content tree
+
+/// A deserializer which reads a content tree.
+///
+/// This behaves like a self-describing format: structs are read as maps
+/// from field names, newtype structs are transparent, and enums are read
+/// either from their variant or, as in JSON, from a string or a single-entry map.
+#[derive(Clone, Debug)]
+pub struct Deserializer {
+    content: Content,
+}
+
+impl Deserializer {
+    /// Create a deserializer which reads the given content.
+    pub fn new(content: Content) -> Deserializer {
+        Deserializer { content }
+    }
+}
+
+macro_rules! forward_to_deserialize {
+    ($($method:ident($($arg:ident : $ty:ty),*);)*) => {
+        $(
+            fn $method<V>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, RoundTripError> where
+                V: Visitor<'de>,
+            {
+                $(let _ = $arg;)*
+                de::Deserializer::deserialize_any(self, visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer {
+    type Error = RoundTripError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, RoundTripError> where
+        V: Visitor<'de>,
+    {
+        match self.content {
+            Content::Bool(v) => visitor.visit_bool(v),
+            Content::I8(v) => visitor.visit_i8(v),
+            Content::I16(v) => visitor.visit_i16(v),
+            Content::I32(v) => visitor.visit_i32(v),
+            Content::I64(v) => visitor.visit_i64(v),
+            Content::I128(v) => visitor.visit_i128(v),
+            Content::U8(v) => visitor.visit_u8(v),
+            Content::U16(v) => visitor.visit_u16(v),
+            Content::U32(v) => visitor.visit_u32(v),
+            Content::U64(v) => visitor.visit_u64(v),
+            Content::U128(v) => visitor.visit_u128(v),
+            Content::F32(v) => visitor.visit_f32(v),
+            Content::F64(v) => visitor.visit_f64(v),
+            Content::Char(v) => visitor.visit_char(v),
+            Content::String(v) => visitor.visit_string(v),
+            Content::Bytes(v) => visitor.visit_byte_buf(v),
+            Content::None => visitor.visit_none(),
+            Content::Some(v) => visitor.visit_some(Deserializer::new(*v)),
+            Content::Unit | Content::UnitStruct(_) => visitor.visit_unit(),
+            Content::NewtypeStruct(_, v) => visitor.visit_newtype_struct(Deserializer::new(*v)),
+            Content::Seq(v) | Content::Tuple(v) | Content::TupleStruct(_, v) => visit_seq(v, visitor),
+            Content::Map(v) => visitor.visit_map(MapDeserializer::new(v)),
+            Content::Struct(_, v) => visit_struct(v, visitor),
+            Content::UnitVariant(_, _, variant) => visitor.visit_str(variant),
+            Content::NewtypeVariant(_, _, variant, v) => {
+                visitor.visit_map(MapDeserializer::new(vec![(Content::String(variant.to_owned()), *v)]))
+            },
+            Content::TupleVariant(_, _, variant, v) => {
+                visitor.visit_map(MapDeserializer::new(vec![(Content::String(variant.to_owned()), Content::Seq(v))]))
+            },
+            Content::StructVariant(name, _, variant, v) => {
+                visitor.visit_map(MapDeserializer::new(vec![(Content::String(variant.to_owned()), Content::Struct(name, v))]))
+            },
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, RoundTripError> where
+        V: Visitor<'de>,
+    {
+        match self.content {
+            Content::None => visitor.visit_none(),
+            Content::Some(v) => visitor.visit_some(Deserializer::new(*v)),
+            content => visitor.visit_some(Deserializer::new(content)),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, RoundTripError> where
+        V: Visitor<'de>,
+    {
+        match self.content {
+            Content::NewtypeStruct(_, v) => visitor.visit_newtype_struct(Deserializer::new(*v)),
+            content => visitor.visit_newtype_struct(Deserializer::new(content)),
+        }
+    }
+
+    fn deserialize_enum<V>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, RoundTripError> where
+        V: Visitor<'de>,
+    {
+        let (variant, content) = match self.content {
+            Content::UnitVariant(_, _, variant) => (variant.to_owned(), None),
+            Content::NewtypeVariant(_, _, variant, v) => (variant.to_owned(), Some(VariantContent::Newtype(*v))),
+            Content::TupleVariant(_, _, variant, v) => (variant.to_owned(), Some(VariantContent::Seq(v))),
+            Content::StructVariant(_, _, variant, v) => (variant.to_owned(), Some(VariantContent::Struct(v))),
+            Content::String(variant) => (variant, None),
+            Content::Map(mut entries) => {
+                if entries.len() != 1 {
+                    return Err(de::Error::invalid_value(Unexpected::Map, &"map with a single key"));
+                }
+                match entries.pop() {
+                    Some((Content::String(variant), v)) => (variant, Some(VariantContent::Any(v))),
+                    Some((key, _)) => return Err(de::Error::invalid_type(key.unexpected(), &"string")),
+                    None => unreachable!(),
+                }
+            },
+            content => return Err(de::Error::invalid_type(content.unexpected(), &"enum")),
+        };
+        visitor.visit_enum(EnumDeserializer { variant, content })
+    }
+
+    forward_to_deserialize! {
+        deserialize_bool();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_u128();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_i128();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_identifier();
+        deserialize_ignored_any();
+    }
+}
+
+struct MissingFieldDeserializer {
+    field: &'static str,
+}
+
+impl<'de> de::Deserializer<'de> for MissingFieldDeserializer {
+    type Error = RoundTripError;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, RoundTripError> where
+        V: Visitor<'de>,
+    {
+        Err(de::Error::missing_field(self.field))
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, RoundTripError> where
+        V: Visitor<'de>,
+    {
+        visitor.visit_none()
+    }
+
+    forward_to_deserialize! {
+        deserialize_bool();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_u128();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_i128();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_newtype_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_identifier();
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]);
+        deserialize_ignored_any();
+    }
+}
+
+fn visit_seq<'de, V>(elements: Vec<Content>, visitor: V) -> Result<V::Value, RoundTripError> where
+    V: Visitor<'de>,
+{
+    let mut deserializer = SeqDeserializer { elements: elements.into_iter() };
+    let result = visitor.visit_seq(&mut deserializer)?;
+    match deserializer.elements.len() {
+        0 => Ok(result),
+        remaining => Err(de::Error::invalid_length(remaining, &"fewer elements in sequence")),
+    }
+}
+
+fn visit_struct<'de, V>(fields: Vec<(&'static str, Content)>, visitor: V) -> Result<V::Value, RoundTripError> where
+    V: Visitor<'de>,
+{
+    let entries = fields.into_iter()
+        .map(|(key, value)| (Content::String(key.to_owned()), value))
+        .collect();
+    visitor.visit_map(MapDeserializer::new(entries))
+}
+
+struct SeqDeserializer {
+    elements: ::alloc::vec::IntoIter<Content>,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqDeserializer {
+    type Error = RoundTripError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, RoundTripError> where
+        T: DeserializeSeed<'de>,
+    {
+        match self.elements.next() {
+            Some(element) => seed.deserialize(Deserializer::new(element)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.elements.len())
+    }
+}
+
+struct MapDeserializer {
+    entries: ::alloc::vec::IntoIter<(Content, Content)>,
+    value: Option<Content>,
+}
+
+impl MapDeserializer {
+    fn new(entries: Vec<(Content, Content)>) -> MapDeserializer {
+        MapDeserializer { entries: entries.into_iter(), value: None }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for MapDeserializer {
+    type Error = RoundTripError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, RoundTripError> where
+        K: DeserializeSeed<'de>,
+    {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(Deserializer::new(key)).map(Some)
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, RoundTripError> where
+        V: DeserializeSeed<'de>,
+    {
+        match self.value.take() {
+            Some(value) => seed.deserialize(Deserializer::new(value)),
+            None => Err(de::Error::custom("map value deserialized before its key")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+enum VariantContent {
+    Newtype(Content),
+    Seq(Vec<Content>),
+    Struct(Vec<(&'static str, Content)>),
+    Any(Content),
+}
+
+struct EnumDeserializer {
+    variant: String,
+    content: Option<VariantContent>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumDeserializer {
+    type Error = RoundTripError;
+    type Variant = VariantDeserializer;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, VariantDeserializer), RoundTripError> where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(Deserializer::new(Content::String(self.variant)))?;
+        Ok((variant, VariantDeserializer { content: self.content }))
+    }
+}
+
+struct VariantDeserializer {
+    content: Option<VariantContent>,
+}
+
+impl<'de> de::VariantAccess<'de> for VariantDeserializer {
+    type Error = RoundTripError;
+
+    fn unit_variant(self) -> Result<(), RoundTripError> {
+        match self.content {
+            None | Some(VariantContent::Any(Content::Unit)) => Ok(()),
+            Some(_) => Err(de::Error::invalid_type(Unexpected::NewtypeVariant, &"unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, RoundTripError> where
+        T: DeserializeSeed<'de>,
+    {
+        match self.content {
+            Some(VariantContent::Newtype(v)) | Some(VariantContent::Any(v)) => seed.deserialize(Deserializer::new(v)),
+            Some(VariantContent::Seq(_)) => Err(de::Error::invalid_type(Unexpected::TupleVariant, &"newtype variant")),
+            Some(VariantContent::Struct(_)) => Err(de::Error::invalid_type(Unexpected::StructVariant, &"newtype variant")),
+            None => Err(de::Error::invalid_type(Unexpected::UnitVariant, &"newtype variant")),
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, RoundTripError> where
+        V: Visitor<'de>,
+    {
+        match self.content {
+            Some(VariantContent::Seq(v)) => visit_seq(v, visitor),
+            Some(VariantContent::Any(v)) => de::Deserializer::deserialize_any(Deserializer::new(v), visitor),
+            Some(VariantContent::Newtype(_)) => Err(de::Error::invalid_type(Unexpected::NewtypeVariant, &"tuple variant")),
+            Some(VariantContent::Struct(_)) => Err(de::Error::invalid_type(Unexpected::StructVariant, &"tuple variant")),
+            None => Err(de::Error::invalid_type(Unexpected::UnitVariant, &"tuple variant")),
+        }
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, RoundTripError> where
+        V: Visitor<'de>,
+    {
+        match self.content {
+            Some(VariantContent::Struct(v)) => visit_struct(v, visitor),
+            Some(VariantContent::Any(v)) => de::Deserializer::deserialize_any(Deserializer::new(v), visitor),
+            Some(VariantContent::Newtype(_)) => Err(de::Error::invalid_type(Unexpected::NewtypeVariant, &"struct variant")),
+            Some(VariantContent::Seq(_)) => Err(de::Error::invalid_type(Unexpected::TupleVariant, &"struct variant")),
+            None => Err(de::Error::invalid_type(Unexpected::UnitVariant, &"struct variant")),
+        }
+    }
+}
@@ -1,23 +1,70 @@
+#![allow(non_local_definitions)]
+// The original tests predate running clippy over them, and are kept as they were.
+#![allow(clippy::arc_with_non_send_sync)]
+#![allow(clippy::enum_variant_names)]
+#![allow(clippy::explicit_auto_deref)]
+
+extern crate bincode;
+#[cfg(feature = "indexmap")]
+extern crate indexmap;
+#[cfg(feature = "conformance")]
+extern crate proptest;
 extern crate serde;
 #[macro_use] extern crate serde_derive;
+extern crate serde_bytes;
 extern crate serde_json;
-extern crate serde_roundtrip;
+#[macro_use] extern crate serde_roundtrip;
 #[macro_use] extern crate serde_roundtrip_derive;
 
 use serde_json::{to_string, from_str};
+use serde_roundtrip::checked_round_trip;
+use serde_roundtrip::IntoRoundTrip;
 use serde_roundtrip::RoundTrip;
+use serde_roundtrip::RoundTripError;
+use serde_roundtrip::RoundTripIn;
+use serde_roundtrip::TryRoundTrip;
 
 use std::borrow::Cow;
+use std::collections::BTreeMap;
+#[cfg(feature = "std")]
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::iter::FromIterator;
+#[cfg(feature = "std")]
 use std::net::IpAddr;
+#[cfg(feature = "std")]
+use std::path::PathBuf;
+#[cfg(feature = "std")]
 use std::rc::Rc;
+#[cfg(feature = "std")]
 use std::str::FromStr;
+#[cfg(feature = "std")]
 use std::sync::Arc;
+#[cfg(feature = "std")]
 use std::time::Duration;
 
+// Whether $s: RoundTripIn<'de, $f, $t>, which is decided at compile time: the method of Holds
+// is only found if the bound holds, and otherwise the method of Fails is found by autoref,
+// so one of the two traits is always unused.
+macro_rules! round_trips_in {
+    ($s:ty => $t:ty, $f:ty) => {{
+        struct Probe<S, T, F>(::std::marker::PhantomData<(S, T, F)>);
+        #[allow(dead_code)]
+        trait Holds { fn holds(&self) -> bool { true } }
+        impl<S, T, F> Holds for Probe<S, T, F> where
+            S: for<'de> RoundTripIn<'de, F, T>,
+            T: ::serde::de::DeserializeOwned,
+            F: ::serde_roundtrip::format::Format,
+        {}
+        #[allow(dead_code)]
+        trait Fails { fn holds(&self) -> bool { false } }
+        impl<S, T, F> Fails for &Probe<S, T, F> {}
+        (&Probe::<$s, $t, $f>(::std::marker::PhantomData)).holds()
+    }};
+}
+
 #[test]
+#[cfg(feature = "std")]
 fn test_round_trip() {
     type Target = (
         (usize,),
@@ -34,7 +81,7 @@ fn test_round_trip() {
         &["hello","world"][..],
         true,
         Arc::new(Rc::new(Box::new(Duration::new(1000,0)))),
-        HashMap::from_iter(vec![ ("a",1) ]),
+        HashMap::<_, _>::from_iter(vec![ ("a",1) ]),
         Cow::Borrowed("x"),
     );
 
@@ -44,6 +91,7 @@ fn test_round_trip() {
 }
 
 #[test]
+#[allow(dead_code)]
 fn test_round_trip_derive() {
     trait Silly<T> {}
     impl<T,U> Silly<T> for U {}
@@ -78,3 +126,1225 @@ fn test_round_trip_derive() {
     let via_round_trip: Target = source.round_trip();
     assert_eq!(via_json, via_round_trip);
 }
+
+#[test]
+fn test_round_trip_serde_attributes() {
+    mod as_string {
+        use serde::Deserialize;
+        use serde::Deserializer;
+        use serde::Serializer;
+        use serde::de::Error;
+
+        pub fn serialize<S: Serializer>(data: &u32, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(&data.to_string())
+        }
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+            String::deserialize(deserializer)?.parse().map_err(D::Error::custom)
+        }
+    }
+
+    fn seven() -> usize { 7 }
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestSettings<T> {
+        #[serde(rename = "title")] name: T,
+        #[serde(rename(serialize = "old", deserialize = "new"))] renamed: Option<T>,
+        #[serde(skip_serializing, default = "seven")] count: usize,
+        #[serde(skip_deserializing)] cache: Vec<T>,
+        #[serde(skip_serializing_if = "Option::is_none")] note: Option<T>,
+        #[serde(with = "as_string")] port: u32,
+    }
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    #[serde(default)]
+    struct TestDefaults { #[serde(skip_serializing)] level: u8, enabled: bool }
+
+    impl Default for TestDefaults {
+        fn default() -> TestDefaults { TestDefaults { level: 3, enabled: false } }
+    }
+
+    type Target = (TestSettings<String>, TestDefaults);
+    let source = (
+        TestSettings { name: "server", renamed: Some("lost"), count: 1, cache: vec!["x"], note: None, port: 8080 },
+        TestDefaults { level: 9, enabled: true },
+    );
+
+    let via_json: Target = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: Target = source.round_trip();
+    let via_try_round_trip: Target = source.try_round_trip().unwrap();
+    assert_eq!(via_json, via_round_trip);
+    assert_eq!(via_json, via_try_round_trip);
+    assert_eq!(via_round_trip.0.renamed, None);
+    assert_eq!(via_round_trip.0.count, 7);
+    assert_eq!(via_round_trip.1.level, 3);
+
+    let mut via_round_trip_into: Target = (
+        TestSettings { name: String::new(), renamed: None, count: 0, cache: vec![String::from("y")], note: None, port: 0 },
+        TestDefaults { level: 0, enabled: false },
+    );
+    source.round_trip_into(&mut via_round_trip_into);
+    assert_eq!(via_json, via_round_trip_into);
+    assert_eq!(via_json, source.into_round_trip());
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestRequired { #[serde(skip_serializing)] id: u64 }
+
+    let via_json: Result<TestRequired, _> = from_str(&to_string(&TestRequired { id: 1 }).unwrap());
+    let via_round_trip: Result<TestRequired, RoundTripError> = TestRequired { id: 1 }.try_round_trip();
+    assert!(via_json.is_err());
+    assert_eq!(via_round_trip.unwrap_err().message(), "missing field `id`");
+
+    // Whether a field which may be skipped is in the data is only known in self-describing formats.
+    use serde_roundtrip::format::Bincode;
+    use serde_roundtrip::format::Json;
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestOptional { #[serde(skip_serializing_if = "Option::is_none")] a: Option<u32>, b: u32 }
+
+    let source = TestOptional { a: None, b: 7 };
+    let via_bincode: Result<TestOptional, _> = bincode::deserialize(&bincode::serialize(&source).unwrap());
+    let via_json: TestOptional = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: TestOptional = source.round_trip_in(Json).unwrap();
+    assert!(via_bincode.is_err());
+    assert_eq!(via_json, via_round_trip);
+    assert!(!round_trips_in!(TestOptional => TestOptional, Bincode));
+    assert!(!round_trips_in!(TestRequired => TestRequired, Bincode));
+
+    // A field which is skipped on both sides is not in the data in any format.
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestCached { #[serde(skip)] cache: Vec<u8>, b: u32 }
+
+    let source = TestCached { cache: vec![1], b: 7 };
+    let via_bincode: TestCached = bincode::deserialize(&bincode::serialize(&source).unwrap()).unwrap();
+    let via_round_trip: TestCached = source.round_trip_in(Bincode).unwrap();
+    assert_eq!(via_bincode, via_round_trip);
+}
+
+#[test]
+fn test_round_trip_enum_representations() {
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestPoint<T> { label: T }
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    #[serde(tag = "type")]
+    enum TestInternal<T> { Point(TestPoint<T>), Move { dx: i32 }, Stop, Code(u8) }
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    #[serde(tag = "t", content = "c")]
+    enum TestAdjacent<T> { Point(TestPoint<T>), Pair(T, u8), Stop }
+
+    // Untagged enums deserialize as the first variant which matches, so 5 arrives as a float.
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    #[serde(untagged)]
+    #[roundtrip(via_serde)]
+    enum TestUntagged { Float(f64), Integer(u64) }
+
+    type Target = (Vec<TestInternal<String>>, Vec<TestAdjacent<String>>, TestUntagged);
+    let source = (
+        vec![TestInternal::Point(TestPoint { label: "a" }), TestInternal::Move { dx: -1 }, TestInternal::Stop],
+        vec![TestAdjacent::Point(TestPoint { label: "b" }), TestAdjacent::Pair("c", 1), TestAdjacent::Stop],
+        TestUntagged::Integer(5),
+    );
+
+    let via_json: Target = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: Target = source.round_trip();
+    let via_into_round_trip: Target = source.into_round_trip();
+    assert_eq!(via_json, via_round_trip);
+    assert_eq!(via_json, via_into_round_trip);
+    assert_eq!(via_round_trip.2, TestUntagged::Float(5.0));
+
+    let source: TestInternal<&str> = TestInternal::Code(7);
+    let via_json = to_string(&source);
+    let via_round_trip: Result<TestInternal<String>, RoundTripError> = source.try_round_trip();
+    assert_eq!(via_json.unwrap_err().to_string(), via_round_trip.unwrap_err().message());
+
+    // Enums which are not externally tagged are deserialized with deserialize_any
+    // or deserialize_identifier, so they only round trip in self-describing formats, and not in bincode.
+    use serde_roundtrip::format::Bincode;
+    use serde_roundtrip::format::Json;
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    enum TestExternal<T> { Pair(T, u8), Stop }
+
+    let source: TestInternal<String> = TestInternal::Move { dx: -1 };
+    let via_bincode: Result<TestInternal<String>, _> = bincode::deserialize(&bincode::serialize(&source).unwrap());
+    assert!(via_bincode.is_err());
+    assert!(!round_trips_in!(TestInternal<String> => TestInternal<String>, Bincode));
+    assert!(round_trips_in!(TestInternal<String> => TestInternal<String>, Json));
+
+    let source = TestUntagged::Integer(5);
+    let via_bincode: Result<TestUntagged, _> = bincode::deserialize(&bincode::serialize(&source).unwrap());
+    assert!(via_bincode.is_err());
+    assert!(!round_trips_in!(TestUntagged => TestUntagged, Bincode));
+    assert!(round_trips_in!(TestUntagged => TestUntagged, Json));
+
+    let source: TestAdjacent<String> = TestAdjacent::Pair(String::from("c"), 1);
+    let via_bincode: Result<TestAdjacent<String>, _> = bincode::deserialize(&bincode::serialize(&source).unwrap());
+    assert!(via_bincode.is_err());
+    assert!(!round_trips_in!(TestAdjacent<String> => TestAdjacent<String>, Bincode));
+    assert!(round_trips_in!(TestAdjacent<String> => TestAdjacent<String>, Json));
+
+    let source = vec![TestExternal::Pair("d", 2), TestExternal::Stop];
+    let via_bincode: Vec<TestExternal<String>> = bincode::deserialize(&bincode::serialize(&source).unwrap()).unwrap();
+    let via_round_trip: Vec<TestExternal<String>> = source.round_trip_in(Bincode).unwrap();
+    assert_eq!(via_bincode, via_round_trip);
+}
+
+#[test]
+fn test_round_trip_target() {
+    use serde_roundtrip::format::Json;
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestMsg { id: u32, name: String, tags: Vec<String>, note: Option<String> }
+
+    #[derive(Serialize, RoundTrip)]
+    #[roundtrip(target = "TestMsg")]
+    #[allow(dead_code)]
+    struct TestMsgRef<'a> {
+        id: u32,
+        #[serde(rename = "name")] title: &'a str,
+        tags: &'a [String],
+        #[serde(skip_serializing_if = "Option::is_none")] note: Option<&'a str>,
+        #[serde(skip_serializing)] cached: bool,
+    }
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    enum TestEvent { Sent(TestMsg), Dropped { id: u32 } }
+
+    #[derive(Serialize, RoundTrip)]
+    #[roundtrip(target = "TestEvent", self_describing)]
+    enum TestEventRef<'a> { Sent(TestMsgRef<'a>), Dropped { id: u32 } }
+
+    let tags = vec![String::from("urgent")];
+    let source = vec![
+        TestEventRef::Sent(TestMsgRef { id: 1, title: "hello", tags: &tags, note: None, cached: true }),
+        TestEventRef::Sent(TestMsgRef { id: 2, title: "world", tags: &[], note: Some("fyi"), cached: false }),
+        TestEventRef::Dropped { id: 3 },
+    ];
+
+    let via_json: Vec<TestEvent> = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: Vec<TestEvent> = source.round_trip();
+    let via_try_round_trip: Vec<TestEvent> = source.try_round_trip().unwrap();
+    let via_round_trip_in: Vec<TestEvent> = source.round_trip_in(Json).unwrap();
+    let via_into_round_trip: Vec<TestEvent> = source.into_round_trip();
+    assert_eq!(via_json, via_round_trip);
+    assert_eq!(via_json, via_try_round_trip);
+    assert_eq!(via_json, via_round_trip_in);
+    assert_eq!(via_json, via_into_round_trip);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_round_trip_borrowed() {
+    use std::path::Path;
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestWrapper<T>(T);
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestMsg<'a> { id: u32, name: &'a str, #[serde(borrow)] tags: Vec<&'a str>, #[serde(borrow)] note: Cow<'a, str> }
+
+    let source = String::from("hello");
+    let via_round_trip: &str = source.round_trip();
+    assert_eq!(via_round_trip, "hello");
+    assert_eq!(via_round_trip.as_ptr(), source.as_ptr());
+
+    let source = TestWrapper(String::from("hello"));
+    let json = to_string(&source).unwrap();
+    let via_json: TestWrapper<&str> = from_str(&json).unwrap();
+    let via_round_trip: TestWrapper<&str> = source.round_trip();
+    assert_eq!(via_json, via_round_trip);
+    assert_eq!(via_round_trip.0.as_ptr(), source.0.as_ptr());
+
+    let source = TestMsg { id: 1, name: "hello", tags: vec!["a", "b"], note: Cow::Owned(String::from("note")) };
+    let json = to_string(&source).unwrap();
+    let via_json: TestMsg = from_str(&json).unwrap();
+    let via_round_trip: TestMsg = source.round_trip();
+    let via_try_round_trip: TestMsg = source.try_round_trip().unwrap();
+    assert_eq!(via_json, via_round_trip);
+    assert_eq!(via_json, via_try_round_trip);
+
+    let source = PathBuf::from("/tmp");
+    let via_round_trip: &Path = source.try_round_trip().unwrap();
+    assert_eq!(via_round_trip, Path::new("/tmp"));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_try_round_trip() {
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestPaths<T> { paths: Vec<T> }
+
+    type Target = TestPaths<PathBuf>;
+    let source = TestPaths { paths: vec![PathBuf::from("/tmp"), PathBuf::from("hello.txt")] };
+
+    let via_json: Target = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: Result<Target, RoundTripError> = source.try_round_trip();
+    assert_eq!(Ok(via_json), via_round_trip);
+}
+
+#[test]
+#[cfg(all(unix, feature = "std"))]
+fn test_try_round_trip_error() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let source = vec![PathBuf::from(OsStr::from_bytes(b"invalid\xff"))];
+    assert!(to_string(&source).is_err());
+
+    let via_round_trip: Result<Vec<PathBuf>, RoundTripError> = source.try_round_trip();
+    assert_eq!(via_round_trip.unwrap_err().message(), "path contains invalid UTF-8 characters");
+}
+
+#[test]
+fn test_into_round_trip() {
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestMessage<T> { name: T, contents: Vec<T> }
+
+    type Target = TestMessage<String>;
+    let source = TestMessage { name: String::from("hello"), contents: vec![String::from("world")] };
+    let name_ptr = source.name.as_ptr();
+    let contents_ptr = source.contents[0].as_ptr();
+
+    let via_json: Target = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: Target = source.into_round_trip();
+    assert_eq!(via_json, via_round_trip);
+    assert_eq!(name_ptr, via_round_trip.name.as_ptr());
+    assert_eq!(contents_ptr, via_round_trip.contents[0].as_ptr());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_round_trip_into() {
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestFrame<T, M> { title: T, lines: Vec<T>, counts: M }
+
+    type Target = TestFrame<String, HashMap<String, usize>>;
+    let source = TestFrame {
+        title: "frame",
+        lines: vec!["hello", "world"],
+        counts: HashMap::<_, _>::from_iter(vec![ ("hello", 1), ("world", 2) ]),
+    };
+    let mut via_round_trip: Target = TestFrame {
+        title: String::with_capacity(100),
+        lines: vec![String::with_capacity(100), String::from("a"), String::from("b")],
+        counts: HashMap::with_capacity(100),
+    };
+    let title_ptr = via_round_trip.title.as_ptr();
+    let lines_ptr = via_round_trip.lines.as_ptr();
+    let line_ptr = via_round_trip.lines[0].as_ptr();
+
+    let via_json: Target = from_str(&to_string(&source).unwrap()).unwrap();
+    source.round_trip_into(&mut via_round_trip);
+    assert_eq!(via_json, via_round_trip);
+    assert_eq!(title_ptr, via_round_trip.title.as_ptr());
+    assert_eq!(lines_ptr, via_round_trip.lines.as_ptr());
+    assert_eq!(line_ptr, via_round_trip.lines[0].as_ptr());
+    assert!(via_round_trip.counts.capacity() >= 100);
+}
+
+#[test]
+fn test_via_serde() {
+    use serde_roundtrip::via_serde;
+    use serde_roundtrip::via_serde::ViaSerde;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum TestForeign { Unit, Newtype(u8), Tuple(u8, String), Struct { x: Option<f64>, y: () } }
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestWrapper<T> { name: T, #[roundtrip(via_serde)] foreign: Vec<TestForeign> }
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    enum TestWrapperEnum { Foreign(#[roundtrip(via_serde)] TestForeign) }
+
+    type Target = (TestWrapper<String>, TestWrapperEnum);
+    let source = (
+        TestWrapper {
+            name: "hello",
+            foreign: vec![
+                TestForeign::Unit,
+                TestForeign::Newtype(37),
+                TestForeign::Tuple(5, String::from("world")),
+                TestForeign::Struct { x: Some(1.5), y: () },
+            ],
+        },
+        TestWrapperEnum::Foreign(TestForeign::Struct { x: None, y: () }),
+    );
+    let via_json: Target = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: Target = source.round_trip();
+    let via_try_round_trip: Target = source.try_round_trip().unwrap();
+    assert_eq!(via_json, via_round_trip);
+    assert_eq!(via_json, via_try_round_trip);
+
+    let source = (vec!["a", "b"], Some((1u8, 'c')));
+    let via_json: (Vec<String>, Option<(u8, char)>) = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_serde: (Vec<String>, Option<(u8, char)>) = ViaSerde(&source).round_trip();
+    assert_eq!(via_json, via_serde);
+
+    let result: Result<Vec<u8>, RoundTripError> = via_serde::round_trip(&"not a sequence");
+    assert!(result.is_err());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_checked_round_trip_agrees() {
+    type Target = (
+        (usize,),
+        Vec<IpAddr>,
+        Vec<String>,
+        Box<Rc<bool>>,
+        Duration,
+        HashMap<String, usize>,
+        Cow<'static, str>,
+    );
+    let source = (
+        (37,),
+        vec![IpAddr::from_str("127.0.0.1").unwrap(), IpAddr::from_str("2001:0db8:85a3:0000:0000:8a2e:0370:7334").unwrap()],
+        &["hello","world"][..],
+        true,
+        Rc::new(Box::new(Duration::new(1000,0))),
+        HashMap::<_, _>::from_iter(vec![ ("a",1) ]),
+        Cow::Borrowed("x"),
+    );
+
+    let via_json: Target = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_checked_round_trip: Target = checked_round_trip(&source);
+    assert_eq!(via_json, via_checked_round_trip);
+}
+
+#[test]
+#[should_panic(expected = "U8(1) != U8(0) at .lines[0].1.0")]
+fn test_checked_round_trip() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct TestBroken(u8);
+
+    impl<'de> RoundTrip<'de, TestBroken> for TestBroken {
+        fn round_trip(&'de self) -> TestBroken { TestBroken(self.0 + 1) }
+    }
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestLines<T> { lines: Vec<(bool, T)> }
+
+    let source = TestLines { lines: vec![(true, 0u8), (false, 5u8)] };
+    let _: TestLines<u8> = checked_round_trip(&source);
+    let source = TestLines { lines: vec![(true, TestBroken(0)), (false, TestBroken(5))] };
+    let _: TestLines<TestBroken> = checked_round_trip(&source);
+}
+
+#[test]
+fn test_checked_round_trip_wide_integers() {
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct Big {
+        x: u128,
+        y: i128,
+    }
+
+    let source = Big { x: u128::MAX, y: -1 };
+    let via_json: Big = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_bincode: Big = bincode::deserialize(&bincode::serialize(&source).unwrap()).unwrap();
+    let via_checked_round_trip: Big = checked_round_trip(&source);
+    assert_eq!(via_json, via_checked_round_trip);
+    assert_eq!(via_bincode, via_checked_round_trip);
+
+    let source = BTreeMap::from_iter(vec![ (i128::MIN, u128::MAX) ]);
+    let via_checked_round_trip: BTreeMap<i128, u128> = checked_round_trip(&source);
+    assert_eq!(source, via_checked_round_trip);
+}
+
+#[test]
+#[should_panic(expected = "round trip differs from serde: U8(6) != U8(5) at .contents[2].StructCase.0")]
+fn test_assert_round_trip() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct TestBroken(u8);
+
+    impl<'de> RoundTrip<'de, TestBroken> for TestBroken {
+        fn round_trip(&'de self) -> TestBroken { TestBroken(self.0 + 1) }
+    }
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    enum TestCase<T> { UnitCase, StructCase(T, bool) }
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestContents<T> { contents: Vec<TestCase<T>> }
+
+    let source = TestContents { contents: vec![TestCase::StructCase(0u8, true), TestCase::UnitCase, TestCase::StructCase(5u8, false)] };
+    assert_round_trip!(source => TestContents<u8>);
+    let source = TestContents { contents: vec![TestCase::UnitCase, TestCase::UnitCase, TestCase::StructCase(TestBroken(5), false)] };
+    assert_round_trip!(source => TestContents<TestBroken>);
+}
+
+#[test]
+#[cfg(feature = "conformance")]
+#[should_panic(expected = "round trip differs from JSON: U32(7) != U32(8) at .counts[\"b\"]")]
+fn test_assert_round_trip_in_real_format() {
+    use serde_roundtrip::conformance::RealFormat;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct TestBroken(BTreeMap<String, u32>);
+
+    impl<'de> RoundTrip<'de, HashMap<String, u32>> for TestBroken {
+        fn round_trip(&'de self) -> HashMap<String, u32> { self.0.iter().map(|(key, &value)| (key.clone(), value - 1)).collect() }
+    }
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestCounts<T> { counts: T }
+
+    let source = TestCounts { counts: BTreeMap::from_iter(vec![(String::from("a"), 1), (String::from("b"), 2)]) };
+    assert_round_trip!(source => TestCounts<HashMap<String, u32>>, RealFormat::Json);
+    assert_round_trip!(source => TestCounts<HashMap<String, u32>>, RealFormat::Bincode);
+    let source = TestCounts { counts: TestBroken(BTreeMap::from_iter(vec![(String::from("b"), 8)])) };
+    assert_round_trip!(source => TestCounts<HashMap<String, u32>>, RealFormat::Json);
+}
+
+#[test]
+fn test_round_trip_in() {
+    use serde_roundtrip::format::AnyFormat;
+    use serde_roundtrip::format::Bincode;
+    use serde_roundtrip::format::Json;
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestReadings<T, M> { name: T, readings: M }
+
+    type Target = TestReadings<String, Vec<Option<f64>>>;
+    let source = TestReadings { name: "sensor", readings: vec![1.5, f64::NAN, f64::INFINITY] };
+    let via_json: Target = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: Target = source.round_trip_in(Json).unwrap();
+    assert_eq!(via_json, via_round_trip);
+    assert_eq!(via_round_trip.readings, vec![Some(1.5), None, None]);
+
+    let via_json: Result<Vec<f64>, _> = from_str(&to_string(&vec![f64::NAN]).unwrap());
+    let via_round_trip: Result<Vec<f64>, RoundTripError> = vec![f64::NAN].round_trip_in(Json);
+    let via_any_format: Result<Vec<f64>, RoundTripError> = vec![f64::NAN].round_trip_in(AnyFormat);
+    assert_eq!(via_round_trip.as_ref().unwrap_err().message(), "invalid type: null, expected f64");
+    assert!(via_json.unwrap_err().to_string().starts_with(via_round_trip.unwrap_err().message()));
+    assert!(via_any_format.unwrap()[0].is_nan());
+
+    let source = BTreeMap::from_iter(vec![ (1u32, "one"), (20, "twenty") ]);
+    let via_json: BTreeMap<String, String> = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: BTreeMap<String, String> = source.round_trip_in(Json).unwrap();
+    assert_eq!(via_json, via_round_trip);
+
+    let source = vec![(1u32, 2u32), (3, 4)];
+    let bytes = bincode::serialize(&source).unwrap();
+    let via_bincode: Vec<[u32; 2]> = bincode::deserialize(&bytes).unwrap();
+    let via_round_trip: Vec<[u32; 2]> = source.round_trip_in(Bincode).unwrap();
+    assert_eq!(via_bincode, via_round_trip);
+}
+
+#[test]
+fn test_round_trip_arrays() {
+    use serde_roundtrip::format::Bincode;
+    use serde_roundtrip::format::Json;
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestKey<T> { name: T, bytes: [u8; 32] }
+
+    let source = TestKey { name: "key", bytes: [7; 32] };
+    let via_json: TestKey<String> = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: TestKey<String> = source.round_trip();
+    assert_eq!(via_json, via_round_trip);
+
+    let source = ["a", "b", "c"];
+    let via_json: [String; 3] = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_try_round_trip: [String; 3] = source.try_round_trip().unwrap();
+    let via_into_round_trip: [String; 3] = source.into_round_trip();
+    let mut via_round_trip_into = [String::from("x"), String::from("y"), String::from("z")];
+    source.round_trip_into(&mut via_round_trip_into);
+    assert_eq!(via_json, via_try_round_trip);
+    assert_eq!(via_json, via_into_round_trip);
+    assert_eq!(via_json, via_round_trip_into);
+
+    let via_json: Vec<String> = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: Vec<String> = source.round_trip_in(Json).unwrap();
+    assert_eq!(via_json, via_round_trip);
+    let via_bincode: Result<Vec<String>, _> = bincode::deserialize(&bincode::serialize(&source).unwrap());
+    assert!(via_bincode.is_err());
+
+    let via_round_trip: Result<[f64; 2], RoundTripError> = [1.5, f64::NAN].round_trip_in(Json);
+    let via_bincode: [f64; 2] = [1.5, f64::NAN].round_trip_in(Bincode).unwrap();
+    assert_eq!(via_round_trip.unwrap_err().message(), "invalid type: null, expected f64");
+    assert!(via_bincode[1].is_nan());
+}
+
+#[test]
+fn test_round_trip_long_arrays() {
+    // Serde only serializes arrays up to length 32, so longer arrays use #[serde(with = "...")].
+    mod long_array {
+        use serde::de::Deserializer;
+        use serde::de::Error;
+        use serde::de::SeqAccess;
+        use serde::de::Visitor;
+        use serde::ser::SerializeTuple;
+        use serde::ser::Serializer;
+        use serde::Deserialize;
+        use serde::Serialize;
+        use std::convert::TryInto;
+        use std::fmt;
+        use std::marker::PhantomData;
+
+        pub fn serialize<S: Serializer, T: Serialize, const N: usize>(data: &[T; N], serializer: S) -> Result<S::Ok, S::Error> {
+            let mut tuple = serializer.serialize_tuple(N)?;
+            for element in data {
+                tuple.serialize_element(element)?;
+            }
+            tuple.end()
+        }
+
+        struct LongArrayVisitor<T, const N: usize>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>, const N: usize> Visitor<'de> for LongArrayVisitor<T, N> {
+            type Value = [T; N];
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "an array of length {}", N)
+            }
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<[T; N], A::Error> {
+                let mut elements = Vec::with_capacity(N);
+                while let Some(element) = seq.next_element()? {
+                    elements.push(element);
+                }
+                let len = elements.len();
+                elements.try_into().map_err(|_| A::Error::invalid_length(len, &self))
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>, T: Deserialize<'de>, const N: usize>(deserializer: D) -> Result<[T; N], D::Error> {
+            deserializer.deserialize_tuple(N, LongArrayVisitor(PhantomData))
+        }
+    }
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestFrame<T> {
+        name: T,
+        #[serde(with = "long_array")] bytes: [u8; 64],
+        #[serde(with = "long_array")] samples: [f32; 256],
+    }
+
+    let samples = core::array::from_fn(|index| index as f32 / 4.0);
+    let source = TestFrame { name: "frame", bytes: [7; 64], samples };
+    let via_json: TestFrame<String> = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_bincode: TestFrame<String> = bincode::deserialize(&bincode::serialize(&source).unwrap()).unwrap();
+    let via_round_trip: TestFrame<String> = source.round_trip();
+    let via_checked_round_trip: TestFrame<String> = checked_round_trip(&source);
+    assert_eq!(via_json, via_round_trip);
+    assert_eq!(via_bincode, via_round_trip);
+    assert_eq!(via_json, via_checked_round_trip);
+}
+
+#[test]
+fn test_round_trip_tuple_structs() {
+    use serde_roundtrip::format::Bincode;
+    use serde_roundtrip::format::Json;
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    #[roundtrip(tuple)]
+    struct TestPoint(f32, f32);
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    #[roundtrip(tuple)]
+    struct TestLabel<'a, T>(&'a str, T);
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    #[roundtrip(tuple)]
+    struct TestMeters(f64);
+
+    let source = TestPoint(1.5, -2.0);
+    let via_json: (f32, f32) = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: (f32, f32) = source.round_trip_in(Json).unwrap();
+    assert_eq!(via_json, via_round_trip);
+    let via_round_trip: TestPoint = source.round_trip_in(Json).unwrap();
+    assert_eq!(via_round_trip, source);
+
+    let source = (1.5f32, -2.0f32);
+    let bytes = bincode::serialize(&source).unwrap();
+    let via_bincode: TestPoint = bincode::deserialize(&bytes).unwrap();
+    let via_round_trip: TestPoint = source.round_trip_in(Bincode).unwrap();
+    assert_eq!(via_bincode, via_round_trip);
+
+    let source = TestLabel("origin", vec![0u8, 0]);
+    let via_json: (String, Vec<u8>) = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: (String, Vec<u8>) = source.round_trip_in(Json).unwrap();
+    assert_eq!(via_json, via_round_trip);
+
+    let source = (String::from("origin"), [0u8, 0]);
+    let json = to_string(&source).unwrap();
+    let via_json: TestLabel<Vec<u8>> = from_str(&json).unwrap();
+    let via_round_trip: TestLabel<Vec<u8>> = source.round_trip_in(Json).unwrap();
+    assert_eq!(via_json, via_round_trip);
+
+    let source = TestMeters(3.5);
+    let bytes = bincode::serialize(&source).unwrap();
+    let via_bincode: (f64,) = bincode::deserialize(&bytes).unwrap();
+    let via_round_trip: (f64,) = source.round_trip_in(Bincode).unwrap();
+    assert_eq!(via_bincode, via_round_trip);
+    let via_json: Result<(f64,), _> = from_str(&to_string(&source).unwrap());
+    assert!(via_json.is_err());
+
+    let via_round_trip: TestMeters = (3.5,).round_trip_in(Bincode).unwrap();
+    assert_eq!(via_round_trip, source);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_round_trip_owned_targets() {
+    use serde::de::DeserializeOwned;
+    use serde_roundtrip::RoundTripOwned;
+    use std::ffi::CStr;
+    use std::ffi::CString;
+    use std::path::Path;
+
+    fn assert_same_as_json<S, T>(source: &S) where
+        S: ?Sized + RoundTripOwned<T>,
+        T: DeserializeOwned + PartialEq + Debug,
+    {
+        let via_json: T = from_str(&to_string(source).unwrap()).unwrap();
+        let via_round_trip: T = source.round_trip();
+        assert_eq!(via_json, via_round_trip);
+    }
+
+    assert_same_as_json::<_, [String; 4]>(&["a", "b", "c", "d"]);
+    assert_same_as_json::<_, Box<str>>("hello");
+    assert_same_as_json::<_, Box<[u32]>>(&vec![1, 2, 3]);
+    assert_same_as_json::<_, Rc<str>>(&String::from("hello"));
+    assert_same_as_json::<_, Arc<str>>(&Box::<str>::from("hello"));
+    assert_same_as_json::<_, Rc<[String]>>(&["a", "b"][..]);
+    assert_same_as_json::<_, Arc<[u32]>>(&Rc::<[u32]>::from(vec![1, 2]));
+    assert_same_as_json::<_, Box<Path>>(Path::new("/tmp"));
+    assert_same_as_json::<_, Rc<CStr>>(&CString::new("hello").unwrap());
+
+    let via_into_round_trip: String = Box::<str>::from("hello").into_round_trip();
+    assert_eq!(via_into_round_trip, "hello");
+    let via_into_round_trip: Vec<String> = Box::<[&str]>::from(vec!["a", "b"]).into_round_trip();
+    assert_eq!(via_into_round_trip, vec!["a", "b"]);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_round_trip_compatible_types() {
+    use serde_bytes::ByteBuf;
+    use serde_roundtrip::format::Bincode;
+    use serde_roundtrip::format::Json;
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestFile<P, N> { path: P, name: N }
+
+    type Target = TestFile<PathBuf, Box<str>>;
+    let source = TestFile { path: String::from("/tmp/x"), name: "x" };
+    let via_json: Target = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: Target = source.round_trip();
+    assert_eq!(via_json, via_round_trip);
+
+    type Target2 = TestFile<String, Rc<str>>;
+    let source = TestFile { path: PathBuf::from("/tmp/x"), name: "x" };
+    let via_json: Target2 = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_try_round_trip: Target2 = source.try_round_trip().unwrap();
+    assert_eq!(via_json, via_try_round_trip);
+
+    let source: &[u8] = b"abc";
+    let via_json: ByteBuf = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_bincode: ByteBuf = bincode::deserialize(&bincode::serialize(&source).unwrap()).unwrap();
+    let via_round_trip: ByteBuf = source.round_trip();
+    let via_into_round_trip: ByteBuf = source.to_vec().into_round_trip();
+    assert_eq!(via_json, via_round_trip);
+    assert_eq!(via_bincode, via_round_trip);
+    assert_eq!(via_json, via_into_round_trip);
+
+    let source = ByteBuf::from(b"abc".to_vec());
+    let via_json: Vec<u8> = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_bincode: Vec<u8> = bincode::deserialize(&bincode::serialize(&source).unwrap()).unwrap();
+    let via_round_trip: Vec<u8> = source.round_trip_in(Json).unwrap();
+    assert_eq!(via_json, via_round_trip);
+    let via_round_trip: Vec<u8> = source.round_trip_in(Bincode).unwrap();
+    assert_eq!(via_bincode, via_round_trip);
+
+    let via_json: String = from_str(&to_string(&'x').unwrap()).unwrap();
+    let via_round_trip: String = 'x'.round_trip_in(Json).unwrap();
+    assert_eq!(via_json, via_round_trip);
+}
+
+#[test]
+fn test_round_trip_numeric_widening() {
+    use serde_roundtrip::format::AnySelfDescribing;
+    use serde_roundtrip::format::Json;
+    use serde_roundtrip::format::WidenIn;
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestSample<C, V> { count: C, value: V }
+
+    type Target = TestSample<i64, f64>;
+    let source = TestSample { count: 200u8, value: -7i32 };
+    let via_json: Target = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: Target = source.round_trip_in(Json).unwrap();
+    assert_eq!(via_json, via_round_trip);
+
+    let source = TestSample { count: 300u16, value: 0.1f32 };
+    let via_json: Result<TestSample<u8, f64>, _> = from_str(&to_string(&source).unwrap());
+    let via_round_trip: Result<TestSample<u8, f64>, RoundTripError> = source.round_trip_in(Json);
+    assert!(via_json.unwrap_err().to_string().starts_with(via_round_trip.unwrap_err().message()));
+
+    let via_json: f64 = from_str(&to_string(&0.1f32).unwrap()).unwrap();
+    let via_round_trip: f64 = 0.1f32.round_trip_in(Json).unwrap();
+    let via_any_self_describing: f64 = 0.1f32.round_trip_in(AnySelfDescribing).unwrap();
+    assert_eq!(via_json, via_round_trip);
+    assert_eq!(via_any_self_describing, f64::from(0.1f32));
+
+    // Some floats are near a tie between two doubles, which serde_json only parses exactly
+    // with its float_roundtrip feature, so check floats spread over all the bit patterns.
+    for bits in (0..u32::MAX).step_by(40_009) {
+        let source = f32::from_bits(bits);
+        if source.is_finite() {
+            let via_json: f64 = from_str(&to_string(&source).unwrap()).unwrap();
+            let via_round_trip: f64 = source.round_trip_in(Json).unwrap();
+            assert_eq!(via_json.to_bits(), via_round_trip.to_bits(), "{:?}", source);
+        }
+    }
+
+    let via_json: Result<f64, _> = from_str(&to_string(&f32::NAN).unwrap());
+    let via_round_trip: Result<f64, RoundTripError> = f32::NAN.round_trip_in(Json);
+    assert!(via_json.unwrap_err().to_string().starts_with(via_round_trip.unwrap_err().message()));
+
+    let via_json: Result<u32, _> = from_str(&to_string(&-1i64).unwrap());
+    let via_round_trip: Result<u32, RoundTripError> = (-1i64).round_trip_in(Json);
+    assert!(via_json.unwrap_err().to_string().starts_with(via_round_trip.unwrap_err().message()));
+
+    // Widening cannot fail, so it has no error to handle, but narrowing can.
+    let via_json: i64 = from_str(&to_string(&200u8).unwrap()).unwrap();
+    let via_widen: i64 = 200u8.widen_in(Json);
+    let via_round_trip: Result<i64, RoundTripError> = 200u8.round_trip_in(Json);
+    assert_eq!(via_json, via_widen);
+    assert_eq!(Ok(via_widen), via_round_trip);
+    let via_widen: f64 = u32::MAX.widen_in(AnySelfDescribing);
+    assert_eq!(via_widen, 4294967295.0);
+    let via_round_trip: Result<u8, RoundTripError> = 200i64.round_trip_in(Json);
+    assert_eq!(via_round_trip, Ok(200));
+    let via_round_trip: Result<u8, RoundTripError> = 300i64.round_trip_in(Json);
+    assert!(via_round_trip.is_err());
+}
+
+#[test]
+fn test_round_trip_schema_evolution() {
+    use serde_roundtrip::format::Json;
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    #[roundtrip(versioned)]
+    struct TestJobV2 { id: Option<u32>, name: String, #[serde(default)] retries: u8, label: Option<String> }
+
+    #[derive(Serialize, RoundTrip)]
+    #[roundtrip(target = "TestJobV2", missing = "retries, label")]
+    struct TestJobV1 { #[roundtrip(some)] id: u32, name: String }
+
+    let source = vec![TestJobV1 { id: 1, name: String::from("build") }, TestJobV1 { id: 2, name: String::from("test") }];
+    let via_json: Vec<TestJobV2> = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: Vec<TestJobV2> = source.round_trip_in(Json).unwrap();
+    assert_eq!(via_json, via_round_trip);
+
+    #[derive(Serialize, RoundTrip)]
+    #[roundtrip(target = "TestJobV2", missing = "name, retries, label")]
+    struct TestJobV0 { id: Option<u32> }
+
+    let source = TestJobV0 { id: None };
+    let via_json: Result<TestJobV2, _> = from_str(&to_string(&source).unwrap());
+    let via_round_trip: Result<TestJobV2, RoundTripError> = source.try_round_trip();
+    assert!(via_json.unwrap_err().to_string().starts_with(via_round_trip.unwrap_err().message()));
+
+    let source = vec![1u32, 2, 3];
+    let via_json: Vec<Option<u64>> = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: Vec<Option<u64>> = source.round_trip_in(Json).unwrap();
+    assert_eq!(via_json, via_round_trip);
+
+    // Values which JSON serializes as null become None, not Some.
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestMarker;
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestProbeV2 { unit: Option<()>, marker: Option<TestMarker>, count: Option<Option<u32>>, value: Option<f64> }
+
+    #[derive(Serialize, RoundTrip)]
+    #[roundtrip(target = "TestProbeV2")]
+    struct TestProbeV1 {
+        #[roundtrip(some)] unit: (),
+        #[roundtrip(some)] marker: TestMarker,
+        #[roundtrip(some)] count: Option<u32>,
+        #[roundtrip(some)] value: f64,
+    }
+
+    for &(count, value) in &[(None, f64::NAN), (Some(3), 0.5)] {
+        let source = TestProbeV1 { unit: (), marker: TestMarker, count, value };
+        let via_json: TestProbeV2 = from_str(&to_string(&source).unwrap()).unwrap();
+        let via_round_trip: TestProbeV2 = source.round_trip_in(Json).unwrap();
+        assert_eq!(via_json, via_round_trip);
+    }
+}
+
+#[test]
+fn test_round_trip_versions() {
+    use serde_roundtrip::format::Json;
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    #[roundtrip(versioned)]
+    struct TestMsgV1 {
+        id: u32,
+        title: String,
+        #[serde(skip_serializing_if = "Vec::is_empty")] tags: Vec<String>,
+        legacy: bool,
+    }
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    #[roundtrip(from = "TestMsgV1")]
+    struct TestMsgV2 {
+        id: u64,
+        #[serde(alias = "title")] #[roundtrip(from = "title")] name: String,
+        #[serde(default)] tags: Vec<String>,
+        #[roundtrip(added)] note: Option<String>,
+        #[serde(default)] #[roundtrip(added)] retries: u8,
+    }
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    #[roundtrip(from = "TestMsgV1")]
+    struct TestMsgV3 { id: u32, #[serde(rename = "title")] name: String, tags: Vec<String> }
+
+    let source = vec![
+        TestMsgV1 { id: 1, title: String::from("hello"), tags: vec![String::from("urgent")], legacy: true },
+        TestMsgV1 { id: 2, title: String::from("world"), tags: vec![], legacy: false },
+    ];
+    let via_json: Vec<TestMsgV2> = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: Vec<TestMsgV2> = source.round_trip_in(Json).unwrap();
+    assert_eq!(via_json, via_round_trip);
+
+    let via_json: TestMsgV3 = from_str(&to_string(&source[0]).unwrap()).unwrap();
+    let via_round_trip: TestMsgV3 = source[0].round_trip_in(Json).unwrap();
+    assert_eq!(via_json, via_round_trip);
+
+    let via_json: Result<TestMsgV3, _> = from_str(&to_string(&source[1]).unwrap());
+    let via_round_trip: Result<TestMsgV3, RoundTripError> = source[1].round_trip_in(Json);
+    assert!(via_json.unwrap_err().to_string().starts_with(via_round_trip.unwrap_err().message()));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_round_trip_std_types() {
+    use std::cell::Cell;
+    use std::cell::RefCell;
+    use std::cmp::Reverse;
+    use std::num::NonZeroU32;
+    use std::num::Saturating;
+    use std::num::Wrapping;
+    use std::ops::Bound;
+    use std::ops::Range;
+    use std::ops::RangeInclusive;
+    use std::sync::Mutex;
+    use std::sync::atomic::AtomicU32;
+    use std::sync::atomic::Ordering;
+    use std::time::SystemTime;
+    use std::time::UNIX_EPOCH;
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestSchedule<T> {
+        id: NonZeroU32,
+        total: u128,
+        counter: Wrapping<u8>,
+        level: Saturating<i16>,
+        priority: Reverse<T>,
+        window: Range<u64>,
+        days: RangeInclusive<u8>,
+        after: Bound<T>,
+        started: SystemTime,
+        cache: Cell<u32>,
+        names: RefCell<Vec<String>>,
+    }
+
+    type Target = TestSchedule<String>;
+    let source = TestSchedule {
+        id: NonZeroU32::new(7).unwrap(),
+        total: u128::MAX,
+        counter: Wrapping(255),
+        level: Saturating(-3),
+        priority: Reverse("high"),
+        window: 10..20,
+        days: 1..=5,
+        after: Bound::Excluded("noon"),
+        started: UNIX_EPOCH + Duration::from_millis(1500),
+        cache: Cell::new(3),
+        names: RefCell::new(vec![String::from("x")]),
+    };
+    let via_json: Target = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: Target = source.round_trip();
+    let via_try_round_trip: Target = source.try_round_trip().unwrap();
+    assert_eq!(via_json, via_round_trip);
+    assert_eq!(via_json, via_try_round_trip);
+
+    let borrowed = source.names.borrow_mut();
+    let via_try_round_trip: Result<Target, RoundTripError> = source.try_round_trip();
+    assert_eq!(via_try_round_trip.unwrap_err().message(), to_string(&source).unwrap_err().to_string());
+    drop(borrowed);
+
+    let source = UNIX_EPOCH - Duration::from_secs(1);
+    let via_try_round_trip: Result<SystemTime, RoundTripError> = source.try_round_trip();
+    assert_eq!(via_try_round_trip.unwrap_err().message(), to_string(&source).unwrap_err().to_string());
+
+    let via_round_trip: u32 = NonZeroU32::new(7).unwrap().round_trip();
+    assert_eq!(via_round_trip, 7);
+    let via_try_round_trip: NonZeroU32 = 7u32.try_round_trip().unwrap();
+    assert_eq!(via_try_round_trip.get(), 7);
+    let via_json: Result<NonZeroU32, _> = from_str(&to_string(&0u32).unwrap());
+    let via_try_round_trip: Result<NonZeroU32, RoundTripError> = 0u32.try_round_trip();
+    assert!(via_json.unwrap_err().to_string().starts_with(via_try_round_trip.unwrap_err().message()));
+
+    let source = Mutex::new(vec!["a", "b"]);
+    let via_json: Mutex<Vec<String>> = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: Mutex<Vec<String>> = source.round_trip();
+    assert_eq!(via_json.into_inner().unwrap(), via_round_trip.into_inner().unwrap());
+
+    let source = AtomicU32::new(9);
+    let via_json: AtomicU32 = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: AtomicU32 = source.round_trip();
+    assert_eq!(via_json.load(Ordering::Relaxed), via_round_trip.load(Ordering::Relaxed));
+}
+
+#[test]
+#[cfg(all(unix, feature = "std"))]
+fn test_round_trip_non_utf8_path() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let source = PathBuf::from(OsStr::from_bytes(b"/tmp/\xff"));
+    let via_try_round_trip: Result<String, RoundTripError> = source.try_round_trip();
+    assert!(to_string(&source).is_err());
+    assert!(via_try_round_trip.is_err());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_round_trip_collections() {
+    use std::cmp::Ordering;
+    use std::collections::BTreeSet;
+    use std::collections::BinaryHeap;
+    use std::collections::HashSet;
+    use std::collections::LinkedList;
+    use std::collections::VecDeque;
+
+    // Entries which are compared by key, so a set keeps only one of the entries with the same key.
+    #[derive(Serialize, Deserialize, RoundTrip, Debug)]
+    struct TestEntry { key: u32, note: String }
+
+    impl PartialEq for TestEntry { fn eq(&self, other: &Self) -> bool { self.key == other.key } }
+    impl Eq for TestEntry {}
+    impl PartialOrd for TestEntry { fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) } }
+    impl Ord for TestEntry { fn cmp(&self, other: &Self) -> Ordering { self.key.cmp(&other.key) } }
+
+    let source = vec![
+        TestEntry { key: 2, note: String::from("first two") },
+        TestEntry { key: 1, note: String::from("one") },
+        TestEntry { key: 2, note: String::from("second two") },
+    ];
+    let via_json: BTreeSet<TestEntry> = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: BTreeSet<TestEntry> = source.round_trip();
+    let notes = |set: &BTreeSet<TestEntry>| set.iter().map(|entry| entry.note.clone()).collect::<Vec<_>>();
+    assert_eq!(notes(&via_json), notes(&via_round_trip));
+    assert_eq!(notes(&via_round_trip), vec!["one", "first two"]);
+
+    let source = vec!["a", "b", "a"];
+    let via_json: VecDeque<String> = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: VecDeque<String> = source.round_trip();
+    assert_eq!(via_json, via_round_trip);
+    let via_json: HashSet<String> = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_try_round_trip: HashSet<String> = source.try_round_trip().unwrap();
+    assert_eq!(via_json, via_try_round_trip);
+    let via_json: LinkedList<String> = from_str(&to_string(&source[..]).unwrap()).unwrap();
+    let via_round_trip: LinkedList<String> = source[..].round_trip();
+    assert_eq!(via_json, via_round_trip);
+
+    let source = BinaryHeap::from(vec![3, 1, 4, 1, 5]);
+    let via_json: Vec<u64> = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_into_round_trip: Vec<u64> = source.into_round_trip();
+    assert_eq!(via_json, via_into_round_trip);
+
+    let source: HashMap<&str, u64> = HashMap::from_iter(vec![ ("a", 1), ("b", 2) ]);
+    let via_json: BTreeMap<String, u64> = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: BTreeMap<String, u64> = source.round_trip();
+    assert_eq!(via_json, via_round_trip);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_round_trip_hashers() {
+    use std::collections::HashSet;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::BuildHasherDefault;
+
+    type TestHasher = BuildHasherDefault<DefaultHasher>;
+
+    let source: HashMap<&str, u32, TestHasher> = HashMap::from_iter(vec![ ("a", 1), ("b", 2) ]);
+    let via_json: HashMap<String, u32> = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: HashMap<String, u32> = source.round_trip();
+    let via_into_round_trip: HashMap<String, u32> = source.clone().into_round_trip();
+    assert_eq!(via_json, via_round_trip);
+    assert_eq!(via_json, via_into_round_trip);
+
+    let source: HashSet<&str> = HashSet::from_iter(vec!["a", "b"]);
+    let via_json: HashSet<String, TestHasher> = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_try_round_trip: HashSet<String, TestHasher> = source.try_round_trip().unwrap();
+    assert_eq!(via_json, via_try_round_trip);
+}
+
+#[test]
+#[cfg(feature = "indexmap")]
+fn test_round_trip_indexmap() {
+    use indexmap::IndexMap;
+    use std::collections::hash_map::RandomState;
+
+    let source: IndexMap<&str, u32, RandomState> = IndexMap::from_iter(vec![ ("b", 2), ("a", 1) ]);
+    let via_json: IndexMap<String, u32, RandomState> = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: IndexMap<String, u32, RandomState> = source.round_trip();
+    assert_eq!(via_json, via_round_trip);
+    assert!(via_json.keys().eq(via_round_trip.keys()));
+
+    let source = BTreeMap::from_iter(vec![ ("b", 2), ("a", 1) ]);
+    let via_json: IndexMap<String, u32, RandomState> = from_str(&to_string(&source).unwrap()).unwrap();
+    let via_round_trip: IndexMap<String, u32, RandomState> = source.round_trip();
+    assert!(via_json.iter().eq(via_round_trip.iter()));
+}
+
+#[test]
+#[cfg(feature = "conformance")]
+fn test_conformance() {
+    use proptest::collection::hash_map;
+    use proptest::collection::vec;
+    use proptest::num::f64;
+    use proptest::option;
+    use proptest::prelude::any;
+    use proptest::strategy::Strategy;
+    use serde_roundtrip::conformance::check_round_trip;
+    use serde_roundtrip::conformance::RealFormat;
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    struct TestMsg<M> { id: u32, name: String, tags: Vec<String>, note: Option<String>, weight: f64, counts: M }
+
+    let strategy = (any::<u32>(), ".*", vec(".*", 0..4), option::of(".*"), f64::NORMAL, hash_map(".*", any::<i64>(), 0..4))
+        .prop_map(|(id, name, tags, note, weight, counts)| TestMsg { id, name, tags, note, weight, counts });
+    check_round_trip::<_, TestMsg<BTreeMap<String, i64>>, _>(strategy, RealFormat::ALL);
+}
+
+#[test]
+#[cfg(feature = "conformance")]
+#[should_panic(expected = "Round trip of TestClamped(10) does not conform")]
+fn test_conformance_counterexample() {
+    use proptest::strategy::Strategy;
+    use serde_roundtrip::conformance::check_round_trip;
+    use serde_roundtrip::conformance::RealFormat;
+    use std::cmp;
+
+    #[derive(Serialize, Debug)]
+    struct TestClamped(u32);
+
+    impl<'de> RoundTrip<'de, u32> for TestClamped {
+        fn round_trip(&'de self) -> u32 { cmp::min(self.0, 9) }
+    }
+
+    check_round_trip::<_, u32, _>((0..1000u32).prop_map(TestClamped), RealFormat::ALL);
+}
+
+#[test]
+#[cfg(feature = "conformance")]
+fn test_fuzz() {
+    use serde_roundtrip::conformance::RealFormat;
+    use serde_roundtrip::format::Json;
+    use serde_roundtrip::fuzz::check_bytes;
+    use serde_roundtrip::fuzz::check_bytes_in;
+    use std::collections::VecDeque;
+
+    type Source = (u32, String, HashMap<String, Vec<bool>>);
+    type Target = (u32, Box<str>, BTreeMap<String, VecDeque<bool>>);
+
+    let mut inputs: Vec<Vec<u8>> = vec![
+        b"[1, \"one\", {\"a\": [true, false], \"b\": []}]".to_vec(),
+        b"[300, \"\", {}]".to_vec(),
+        b"not json".to_vec(),
+        vec![],
+    ];
+    inputs.extend(RealFormat::ALL.iter().map(|format| format.encode(&(7u32, "seven", HashMap::<_, _>::from_iter(vec![("x", vec![true])]))).unwrap()));
+    for bytes in &inputs {
+        for &format in RealFormat::ALL {
+            check_bytes::<Source, Target>(bytes, format);
+        }
+        check_bytes_in::<Vec<u16>, Vec<u8>, _>(bytes, RealFormat::Json, Json);
+        check_bytes_in::<(u32, String), (i64, String), _>(bytes, RealFormat::Json, Json);
+        #[cfg(feature = "arbitrary")]
+        serde_roundtrip::fuzz::check_arbitrary::<Source, Target>(bytes, RealFormat::ALL);
+    }
+    check_bytes_in::<Vec<u16>, Vec<u8>, _>(b"[3, 300]", RealFormat::Json, Json);
+}
+
+#[test]
+#[cfg(feature = "conformance")]
+#[should_panic(expected = "round trip differs from JSON: U8(2) != U8(1) at [0]")]
+fn test_fuzz_counterexample() {
+    use serde_roundtrip::conformance::RealFormat;
+    use serde_roundtrip::fuzz::check_bytes;
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct TestBroken(u8);
+
+    impl<'de> RoundTrip<'de, u8> for TestBroken {
+        fn round_trip(&'de self) -> u8 { self.0.wrapping_add(1) }
+    }
+
+    check_bytes::<Vec<TestBroken>, Vec<u8>>(b"[1, 2]", RealFormat::Json);
+}
+
+#[test]
+#[cfg(all(unix, feature = "std"))]
+fn test_channel() {
+    use serde::Serialize;
+    use serde::de::DeserializeOwned;
+    use serde_roundtrip::channel;
+    use serde_roundtrip::channel::Codec;
+    use serde_roundtrip::channel::RoundTripReceiver;
+    use serde_roundtrip::channel::RoundTripSender;
+    use std::io;
+    use std::os::unix::net::UnixStream;
+    use std::thread;
+
+    struct TestJsonCodec;
+
+    impl Codec for TestJsonCodec {
+        fn encode<S: ?Sized + Serialize>(&self, data: &S) -> Result<Vec<u8>, RoundTripError> {
+            serde_json::to_vec(data).map_err(RoundTripError::new)
+        }
+        fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, RoundTripError> {
+            serde_json::from_slice(bytes).map_err(RoundTripError::new)
+        }
+    }
+
+    #[derive(Serialize, Deserialize, RoundTrip, PartialEq, Debug)]
+    enum TestCommand<T> { Echo(T), Quit }
+
+    // The same code works whichever transport is in use.
+    fn exchange(sender: RoundTripSender<TestCommand<String>>, receiver: RoundTripReceiver<TestCommand<String>>) {
+        let sending = thread::spawn(move || {
+            sender.send(&TestCommand::Echo("hello")).unwrap();
+            sender.send(&TestCommand::Echo("world")).unwrap();
+            sender.send(&TestCommand::Quit::<&str>).unwrap();
+        });
+        assert_eq!(receiver.recv().unwrap(), TestCommand::Echo(String::from("hello")));
+        assert_eq!(receiver.recv().unwrap(), TestCommand::Echo(String::from("world")));
+        assert_eq!(receiver.recv().unwrap(), TestCommand::Quit);
+        sending.join().unwrap();
+        assert_eq!(receiver.recv().unwrap_err().message(), "channel disconnected");
+    }
+
+    let (sender, receiver) = channel::channel();
+    assert!(sender.is_local() && receiver.is_local());
+    exchange(sender, receiver);
+
+    let (writer, reader) = UnixStream::pair().unwrap();
+    let sender = RoundTripSender::from_writer(writer, TestJsonCodec);
+    let receiver = RoundTripReceiver::from_reader(reader, TestJsonCodec);
+    assert!(!sender.is_local() && !receiver.is_local());
+    exchange(sender, receiver);
+
+    // A corrupt length is not trusted, so the receiver does not try to allocate it.
+    let mut bytes = u64::MAX.to_le_bytes().to_vec();
+    bytes.extend_from_slice(b"\"hello\"");
+    let receiver: RoundTripReceiver<String> = RoundTripReceiver::from_reader(io::Cursor::new(bytes), TestJsonCodec);
+    assert_eq!(receiver.recv().unwrap_err().message(), "channel disconnected");
+}